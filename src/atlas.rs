@@ -0,0 +1,439 @@
+use std::collections::HashMap;
+
+use anyhow::Context as _;
+use font_kit::{
+    canvas::{Canvas, Format, RasterizationOptions},
+    font::Font,
+    hinting::HintingOptions,
+    source::Source,
+};
+use pathfinder_geometry::{transform2d::Transform2F, vector::Vector2I};
+
+use crate::{
+    bitmap::BitmapFont,
+    types::FontName,
+    xml::{DynamicFont, FontStyle, VerticalOffset},
+};
+
+// グリフの間の余白 (テクスチャのフィルタリングで隣のグリフが滲まないように)
+const GLYPH_PADDING: u32 = 1;
+
+// ポイントをピクセルに変換 (96dpi)
+pub fn points_to_pixels(size: f32) -> f32 {
+    size * 96.0 / 72.0
+}
+
+// テクスチャに配置したグリフ
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasGlyph {
+    pub c: char,
+    // テクスチャ上のページと位置
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // ペンの位置からビットマップの左端まで
+    pub left: i32,
+    // 行の上端からビットマップの上端まで
+    pub top: i32,
+    pub advance: f32,
+}
+
+// ラスタライズしたグリフを1枚のテクスチャにまとめたもの
+#[derive(Debug, Clone)]
+pub struct GlyphAtlas {
+    pub width: u32,
+    pub height: u32,
+    // ページ毎のグリフのアルファ値 (width * height)
+    pub pages: Vec<Vec<u8>>,
+    // 文字順
+    pub glyphs: Vec<AtlasGlyph>,
+    pub line_height: i32,
+    // 行の上端からベースラインまで
+    pub base: i32,
+    pub warnings: Vec<String>,
+}
+
+// ラスタライズ済みで、まだ配置していないグリフ
+struct RasterGlyph {
+    c: char,
+    width: u32,
+    height: u32,
+    left: i32,
+    // ベースラインからビットマップの上端まで (下向きが正)
+    top: i32,
+    advance: f32,
+    alpha: Vec<u8>,
+}
+
+// フォントのピクセル単位のメトリクス
+struct PixelMetrics {
+    ascent: f32,
+    descent: f32,
+    line_gap: f32,
+}
+
+impl GlyphAtlas {
+    // CharacterRegionの全ての文字をラスタライズする (テクスチャの幅と高さはmax_size以下)
+    pub fn build(font: &DynamicFont, source: &dyn Source, max_size: u32) -> anyhow::Result<Self> {
+        let mut loaded: HashMap<String, Font> = HashMap::new();
+        let mut warnings = Vec::new();
+        let mut raster_glyphs = Vec::new();
+        let mut metrics = Vec::new();
+        let mut skipped = 0;
+
+        let base_metrics = {
+            let base_font = load_font(&mut loaded, &font.font_name, source)?;
+            pixel_metrics(base_font, font.size)
+        };
+
+        for bitmap_override in &font.bitmap_overrides {
+            if bitmap_override.chars.is_empty() {
+                continue;
+            }
+            let bitmap = &bitmap_override.font;
+            metrics.push(PixelMetrics {
+                ascent: bitmap.base as f32,
+                descent: (bitmap.line_height - bitmap.base) as f32,
+                line_gap: 0.0,
+            });
+        }
+
+        for region in &font.character_regions.character_region {
+            let font_name = region.font_name.as_deref().unwrap_or(&font.font_name);
+            let size = region.size.unwrap_or(font.size);
+            let style = region.style.unwrap_or(font.style);
+            let loaded_font = load_font(&mut loaded, font_name, source)?;
+            if style != FontStyle::Regular {
+                let warning = format!("{}: Style {:?} is not synthesized", font_name, style);
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+            metrics.push(pixel_metrics(loaded_font, size));
+
+            for c in region.start..=region.end {
+                // .NETのcharで表せない文字
+                if c as u32 > 0xFFFF {
+                    skipped += 1;
+                    continue;
+                }
+                // ビットマップフォントのグリフはそのまま使用
+                if let Some(bitmap) = font.bitmap_override(c) {
+                    if let Some(glyph) = copy_bitmap_glyph(bitmap, c) {
+                        raster_glyphs.push(glyph);
+                    }
+                    continue;
+                }
+                // グリフが無い文字はDefaultCharacterで表示される
+                if let Some(glyph) = rasterize(loaded_font, c, points_to_pixels(size))? {
+                    raster_glyphs.push(glyph);
+                }
+            }
+        }
+        if skipped > 0 {
+            warnings.push(format!(
+                "{} chars above U+FFFF are not supported by SpriteFont",
+                skipped
+            ));
+        }
+
+        // VerticalOffsetに合わせてベースラインを揃える
+        let (ascent, descent) = match font.vertical_offset {
+            VerticalOffset::DefaultFontAscent => (base_metrics.ascent, base_metrics.descent),
+            VerticalOffset::MaxAscent => metrics.iter().fold(
+                (base_metrics.ascent, base_metrics.descent),
+                |(ascent, descent), m| (ascent.max(m.ascent), descent.max(m.descent)),
+            ),
+        };
+        let base = ascent.round() as i32;
+        let line_height = (ascent + descent + base_metrics.line_gap).round() as i32;
+
+        let mut atlas = pack(raster_glyphs, base, max_size)?;
+        atlas.line_height = line_height;
+        atlas.base = base;
+        atlas.warnings = warnings;
+        Ok(atlas)
+    }
+
+    pub fn glyph(&self, c: char) -> Option<&AtlasGlyph> {
+        self.glyphs
+            .binary_search_by_key(&c, |glyph| glyph.c)
+            .ok()
+            .map(|index| &self.glyphs[index])
+    }
+}
+
+pub fn load_font<'a>(
+    loaded: &'a mut HashMap<String, Font>,
+    font_name: &str,
+    source: &dyn Source,
+) -> anyhow::Result<&'a Font> {
+    if !loaded.contains_key(font_name) {
+        let font = FontName::full(font_name.to_string())
+            .get_font_handle(source)
+            .with_context(|| format!("Font not found: {}", font_name))?
+            .load()
+            .with_context(|| format!("Failed to load font: {}", font_name))?;
+        loaded.insert(font_name.to_string(), font);
+    }
+    Ok(&loaded[font_name])
+}
+
+fn pixel_metrics(font: &Font, size: f32) -> PixelMetrics {
+    let metrics = font.metrics();
+    let scale = points_to_pixels(size) / metrics.units_per_em as f32;
+    PixelMetrics {
+        ascent: metrics.ascent * scale,
+        descent: -metrics.descent * scale,
+        line_gap: metrics.line_gap * scale,
+    }
+}
+
+fn rasterize(font: &Font, c: char, pixel_size: f32) -> anyhow::Result<Option<RasterGlyph>> {
+    let Some(glyph_id) = font.glyph_for_char(c) else {
+        return Ok(None);
+    };
+    let scale = pixel_size / font.metrics().units_per_em as f32;
+    let advance = font.advance(glyph_id)?.x() * scale;
+    let bounds = font.raster_bounds(
+        glyph_id,
+        pixel_size,
+        Transform2F::default(),
+        HintingOptions::None,
+        RasterizationOptions::GrayscaleAa,
+    )?;
+
+    let (width, height) = (bounds.width().max(0) as u32, bounds.height().max(0) as u32);
+    let mut alpha = Vec::new();
+    if width > 0 && height > 0 {
+        let mut canvas = Canvas::new(Vector2I::new(width as i32, height as i32), Format::A8);
+        font.rasterize_glyph(
+            &mut canvas,
+            glyph_id,
+            pixel_size,
+            Transform2F::from_translation(-bounds.origin().to_f32()),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )?;
+        for row in canvas.pixels.chunks(canvas.stride) {
+            alpha.extend_from_slice(&row[..width as usize]);
+        }
+    }
+
+    Ok(Some(RasterGlyph {
+        c,
+        width,
+        height,
+        left: bounds.origin_x(),
+        top: bounds.origin_y(),
+        advance,
+        alpha,
+    }))
+}
+
+fn copy_bitmap_glyph(bitmap: &BitmapFont, c: char) -> Option<RasterGlyph> {
+    let glyph = bitmap.glyph(c)?;
+    Some(RasterGlyph {
+        c,
+        width: glyph.width,
+        height: glyph.height,
+        left: glyph.xoffset,
+        top: glyph.yoffset - bitmap.base,
+        advance: glyph.xadvance as f32,
+        alpha: bitmap.glyph_alpha(glyph),
+    })
+}
+
+// テクスチャの幅、高さ、ページ数、各グリフの位置
+type Packing = (u32, u32, usize, Vec<GlyphPosition>);
+
+// グリフのページと位置
+type GlyphPosition = (usize, u32, u32);
+
+// 高さ順に行へ詰める (幅と高さは2の累乗、1枚に収まらない場合はmax_sizeのページに分ける)
+fn pack(mut glyphs: Vec<RasterGlyph>, base: i32, max_size: u32) -> anyhow::Result<GlyphAtlas> {
+    glyphs.sort_by_key(|glyph| glyph.c);
+    glyphs.dedup_by_key(|glyph| glyph.c);
+    let mut order = (0..glyphs.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&index| std::cmp::Reverse(glyphs[index].height));
+
+    // 1枚に収まる場合は、面積が最小になる幅を選ぶ (同じ面積なら正方形に近い方)
+    let mut best: Option<Packing> = None;
+    let mut width = 64;
+    while width <= max_size {
+        if let Some((1, height, positions)) = shelf_positions(&glyphs, &order, width, max_size) {
+            let height = height.next_power_of_two();
+            let is_better = best.as_ref().is_none_or(|(w, h, _, _)| {
+                (width * height, width.max(height)) < (w * h, *w.max(h))
+            });
+            if is_better {
+                best = Some((width, height, 1, positions));
+            }
+        }
+        width *= 2;
+    }
+    let best = best.or_else(|| {
+        shelf_positions(&glyphs, &order, max_size, max_size)
+            .map(|(pages, _, positions)| (max_size, max_size, pages, positions))
+    });
+    let Some((width, height, page_count, positions)) = best else {
+        anyhow::bail!(
+            "Some glyphs do not fit in a {}x{} texture",
+            max_size,
+            max_size
+        );
+    };
+
+    let mut pages = vec![vec![0; (width * height) as usize]; page_count];
+    let mut atlas_glyphs = Vec::with_capacity(glyphs.len());
+    for (glyph, (page, x, y)) in glyphs.into_iter().zip(positions) {
+        for row in 0..glyph.height {
+            let src = (row * glyph.width) as usize;
+            let dst = ((y + row) * width + x) as usize;
+            pages[page][dst..dst + glyph.width as usize]
+                .copy_from_slice(&glyph.alpha[src..src + glyph.width as usize]);
+        }
+        atlas_glyphs.push(AtlasGlyph {
+            c: glyph.c,
+            page,
+            x,
+            y,
+            width: glyph.width,
+            height: glyph.height,
+            left: glyph.left,
+            top: base + glyph.top,
+            advance: glyph.advance,
+        });
+    }
+
+    Ok(GlyphAtlas {
+        width,
+        height,
+        pages,
+        glyphs: atlas_glyphs,
+        line_height: 0,
+        base,
+        warnings: Vec::new(),
+    })
+}
+
+// ページ数、最後のページで使用した高さ、各グリフの位置 (幅に収まらないグリフがあればNone)
+fn shelf_positions(
+    glyphs: &[RasterGlyph],
+    order: &[usize],
+    width: u32,
+    max_height: u32,
+) -> Option<(usize, u32, Vec<GlyphPosition>)> {
+    let mut positions = vec![(0, 0, 0); glyphs.len()];
+    let mut page = 0;
+    let (mut x, mut y, mut shelf_height) = (GLYPH_PADDING, GLYPH_PADDING, 0);
+    for &index in order {
+        let glyph = &glyphs[index];
+        if glyph.width + GLYPH_PADDING * 2 > width || glyph.height + GLYPH_PADDING * 2 > max_height
+        {
+            return None;
+        }
+        if x + glyph.width + GLYPH_PADDING > width {
+            x = GLYPH_PADDING;
+            y += shelf_height + GLYPH_PADDING;
+            shelf_height = 0;
+        }
+        if y + glyph.height + GLYPH_PADDING > max_height {
+            page += 1;
+            x = GLYPH_PADDING;
+            y = GLYPH_PADDING;
+            shelf_height = 0;
+        }
+        positions[index] = (page, x, y);
+        x += glyph.width + GLYPH_PADDING;
+        shelf_height = shelf_height.max(glyph.height);
+    }
+    Some((page + 1, y + shelf_height + GLYPH_PADDING, positions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmap::{BitmapChar, BitmapPage};
+
+    fn raster_glyph(c: char, width: u32, height: u32) -> RasterGlyph {
+        RasterGlyph {
+            c,
+            width,
+            height,
+            left: 0,
+            top: -(height as i32),
+            advance: width as f32,
+            alpha: vec![255; (width * height) as usize],
+        }
+    }
+
+    #[test]
+    fn test_copy_bitmap_glyph() {
+        let bitmap = BitmapFont {
+            face: "Pixel".to_string(),
+            line_height: 10,
+            base: 8,
+            chars: vec![BitmapChar {
+                c: 'A',
+                page: 0,
+                x: 2,
+                y: 1,
+                width: 3,
+                height: 2,
+                xoffset: 1,
+                yoffset: 2,
+                xadvance: 5,
+            }],
+            kernings: Vec::new(),
+            pages: vec![BitmapPage {
+                width: 4,
+                height: 4,
+                alpha: (0..16).collect(),
+            }],
+        };
+        assert!(copy_bitmap_glyph(&bitmap, 'B').is_none());
+        let glyph = copy_bitmap_glyph(&bitmap, 'A').unwrap();
+        assert_eq!((glyph.left, glyph.top, glyph.advance), (1, -6, 5.0));
+        // ページの外は0
+        assert_eq!(glyph.alpha, vec![6, 7, 0, 10, 11, 0]);
+    }
+
+    #[test]
+    fn test_pack() {
+        let glyphs = vec![
+            raster_glyph('a', 30, 20),
+            raster_glyph('b', 30, 40),
+            raster_glyph(' ', 0, 0),
+            raster_glyph('c', 30, 20),
+        ];
+        let atlas = pack(glyphs, 16, 256).unwrap();
+        assert_eq!((atlas.width, atlas.height), (64, 64));
+        // 高さ順に配置し、幅に収まらないグリフは次の行へ
+        let b = atlas.glyph('b').unwrap();
+        assert_eq!((b.x, b.y, b.top), (1, 1, -24));
+        let a = atlas.glyph('a').unwrap();
+        assert_eq!((a.x, a.y), (32, 1));
+        let c = atlas.glyph('c').unwrap();
+        assert_eq!((c.x, c.y), (1, 42));
+        assert_eq!(atlas.pages.len(), 1);
+        assert_eq!(atlas.pages[0][42 * 64 + 1], 255);
+        assert_eq!(atlas.pages[0][42 * 64], 0);
+
+        let glyphs = vec![raster_glyph('a', 300, 20)];
+        assert!(pack(glyphs, 16, 256).is_err());
+
+        // 1枚に収まらない場合はページを分ける
+        let glyphs = vec![
+            raster_glyph('a', 60, 40),
+            raster_glyph('b', 60, 40),
+            raster_glyph('c', 60, 20),
+        ];
+        let atlas = pack(glyphs, 16, 64).unwrap();
+        assert_eq!((atlas.width, atlas.height, atlas.pages.len()), (64, 64, 2));
+        let c = atlas.glyph('c').unwrap();
+        assert_eq!((c.page, c.x, c.y), (1, 1, 42));
+    }
+}
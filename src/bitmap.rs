@@ -0,0 +1,421 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+};
+
+use anyhow::Context as _;
+use quick_xml::{events::Event, Reader};
+
+use crate::{
+    bmfont::KerningPair,
+    types::{CharRange, CharRangeList, FontNameBundle},
+};
+
+// commonのalphaChnlの値 (3: 0、4: 1の場合はアルファチャンネルにグリフが無い)
+const ALPHA_CHANNEL_ZERO: i64 = 3;
+const ALPHA_CHANNEL_ONE: i64 = 4;
+
+// .fntのグリフ (ページ上の位置とピクセル単位のメトリクス)
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitmapChar {
+    pub c: char,
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // ペンの位置からビットマップの左端まで
+    pub xoffset: i32,
+    // 行の上端からビットマップの上端まで
+    pub yoffset: i32,
+    pub xadvance: i32,
+}
+
+// ページのグリフのアルファ値 (width * height)
+#[derive(Debug, Clone)]
+pub struct BitmapPage {
+    pub width: u32,
+    pub height: u32,
+    pub alpha: Vec<u8>,
+}
+
+// 既存のビットマップフォント (AngelCode BMFontの.fntとPNGのページ)
+#[derive(Debug, Clone)]
+pub struct BitmapFont {
+    pub face: String,
+    pub line_height: i32,
+    // 行の上端からベースラインまで
+    pub base: i32,
+    // 文字順
+    pub chars: Vec<BitmapChar>,
+    pub kernings: Vec<KerningPair>,
+    pub pages: Vec<BitmapPage>,
+}
+
+// ページを読み込む前の.fntの内容
+#[derive(Debug)]
+struct Descriptor {
+    face: String,
+    line_height: i32,
+    base: i32,
+    // アルファチャンネルではなく色 (赤) をグリフとして使用
+    color_as_alpha: bool,
+    page_files: Vec<String>,
+    chars: Vec<BitmapChar>,
+    kernings: Vec<KerningPair>,
+}
+
+// .fntの1行または1要素 (タグと属性)
+type Record = (String, HashMap<String, String>);
+
+impl BitmapFont {
+    // .fntとページのPNGを読み込む (テキスト形式とXML形式に対応)
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read(path)
+            .with_context(|| format!("Failed to read bitmap font: {}", path.display()))?;
+        let descriptor = parse_descriptor(&content)
+            .with_context(|| format!("Failed to parse bitmap font: {}", path.display()))?;
+
+        // ページのファイル名は.fntからの相対パス
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let pages = descriptor
+            .page_files
+            .iter()
+            .map(|file| {
+                let page_path = directory.join(file);
+                read_page(&page_path, descriptor.color_as_alpha)
+                    .with_context(|| format!("Failed to read page: {}", page_path.display()))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if let Some(glyph) = descriptor
+            .chars
+            .iter()
+            .find(|glyph| glyph.page >= pages.len())
+        {
+            anyhow::bail!(
+                "{}: Page {} of U+{:04X} is not found",
+                path.display(),
+                glyph.page,
+                glyph.c as u32
+            );
+        }
+
+        Ok(Self {
+            face: descriptor.face,
+            line_height: descriptor.line_height,
+            base: descriptor.base,
+            chars: descriptor.chars,
+            kernings: descriptor.kernings,
+            pages,
+        })
+    }
+
+    // FontNameBundleの代わりに使用する名前 (全てface)
+    pub fn name_bundle(&self) -> FontNameBundle<'static> {
+        FontNameBundle {
+            family: self.face.clone().into(),
+            postscript: self.face.clone().into(),
+            full: self.face.clone().into(),
+        }
+    }
+
+    pub fn characters(&self) -> CharRangeList {
+        self.chars
+            .iter()
+            .map(|glyph| CharRange::new_single(glyph.c as u32))
+            .collect()
+    }
+
+    pub fn glyph(&self, c: char) -> Option<&BitmapChar> {
+        self.chars
+            .binary_search_by_key(&c, |glyph| glyph.c)
+            .ok()
+            .map(|index| &self.chars[index])
+    }
+
+    // グリフの範囲のアルファ値 (ページの外は0)
+    pub fn glyph_alpha(&self, glyph: &BitmapChar) -> Vec<u8> {
+        let page = &self.pages[glyph.page];
+        let mut alpha = Vec::with_capacity((glyph.width * glyph.height) as usize);
+        for y in glyph.y..glyph.y + glyph.height {
+            for x in glyph.x..glyph.x + glyph.width {
+                let value = if x < page.width && y < page.height {
+                    page.alpha[(y * page.width + x) as usize]
+                } else {
+                    0
+                };
+                alpha.push(value);
+            }
+        }
+        alpha
+    }
+}
+
+fn parse_descriptor(content: &[u8]) -> anyhow::Result<Descriptor> {
+    if content.starts_with(b"BMF") {
+        anyhow::bail!("Binary .fnt is not supported, export it as text or XML");
+    }
+    let text = std::str::from_utf8(content).context("The .fnt is not UTF-8")?;
+    let records = if text.trim_start().starts_with('<') {
+        parse_xml(text)?
+    } else {
+        parse_text(text)
+    };
+
+    let mut descriptor = Descriptor {
+        face: String::new(),
+        line_height: 0,
+        base: 0,
+        color_as_alpha: false,
+        page_files: Vec::new(),
+        chars: Vec::new(),
+        kernings: Vec::new(),
+    };
+    let mut has_common = false;
+    for (tag, attributes) in &records {
+        let number = |key: &str| attribute(tag, attributes, key);
+        match tag.as_str() {
+            "info" => {
+                descriptor.face = attributes.get("face").cloned().unwrap_or_default();
+            }
+            "common" => {
+                if attributes.get("packed").is_some_and(|packed| packed != "0") {
+                    anyhow::bail!("Packed channels are not supported");
+                }
+                descriptor.line_height = number("lineHeight")? as i32;
+                descriptor.base = number("base")? as i32;
+                let alpha_channel = number("alphaChnl").unwrap_or(0);
+                descriptor.color_as_alpha =
+                    matches!(alpha_channel, ALPHA_CHANNEL_ZERO | ALPHA_CHANNEL_ONE);
+                has_common = true;
+            }
+            "page" => {
+                let id = number("id")? as usize;
+                let file = attributes
+                    .get("file")
+                    .with_context(|| format!("{}: file is missing", tag))?;
+                if descriptor.page_files.len() <= id {
+                    descriptor.page_files.resize(id + 1, String::new());
+                }
+                descriptor.page_files[id] = file.clone();
+            }
+            "char" => {
+                // id=-1等の文字でないグリフは無視
+                let Some(c) = u32::try_from(number("id")?).ok().and_then(char::from_u32) else {
+                    continue;
+                };
+                descriptor.chars.push(BitmapChar {
+                    c,
+                    page: number("page").unwrap_or(0) as usize,
+                    x: number("x")? as u32,
+                    y: number("y")? as u32,
+                    width: number("width")? as u32,
+                    height: number("height")? as u32,
+                    xoffset: number("xoffset")? as i32,
+                    yoffset: number("yoffset")? as i32,
+                    xadvance: number("xadvance")? as i32,
+                });
+            }
+            "kerning" => {
+                let first = u32::try_from(number("first")?)
+                    .ok()
+                    .and_then(char::from_u32);
+                let second = u32::try_from(number("second")?)
+                    .ok()
+                    .and_then(char::from_u32);
+                if let (Some(first), Some(second)) = (first, second) {
+                    descriptor.kernings.push(KerningPair {
+                        first,
+                        second,
+                        amount: number("amount")? as i32,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !has_common {
+        anyhow::bail!("common is missing");
+    }
+    if descriptor.face.is_empty() {
+        anyhow::bail!("info face is missing");
+    }
+    if let Some(id) = descriptor.page_files.iter().position(String::is_empty) {
+        anyhow::bail!("page {} is missing", id);
+    }
+    descriptor.chars.sort_by_key(|glyph| glyph.c);
+    descriptor.chars.dedup_by_key(|glyph| glyph.c);
+    descriptor
+        .kernings
+        .sort_by_key(|pair| (pair.first, pair.second));
+    Ok(descriptor)
+}
+
+fn attribute(tag: &str, attributes: &HashMap<String, String>, key: &str) -> anyhow::Result<i64> {
+    let value = attributes
+        .get(key)
+        .with_context(|| format!("{}: {} is missing", tag, key))?;
+    value
+        .trim()
+        .parse()
+        .with_context(|| format!("{}: Invalid {}: {}", tag, key, value))
+}
+
+// テキスト形式 (タグ key=value key="value" ...)
+fn parse_text(text: &str) -> Vec<Record> {
+    let mut records = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        let (tag, mut rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        if tag.is_empty() {
+            continue;
+        }
+        let mut attributes = HashMap::new();
+        loop {
+            rest = rest.trim_start();
+            let Some((key, value)) = rest.split_once('=') else {
+                break;
+            };
+            let (value, next) = match value.strip_prefix('"') {
+                Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+                None => value.split_once(char::is_whitespace).unwrap_or((value, "")),
+            };
+            attributes.insert(key.trim().to_string(), value.to_string());
+            rest = next;
+        }
+        records.push((tag.to_string(), attributes));
+    }
+    records
+}
+
+// XML形式 (要素名と属性)
+fn parse_xml(text: &str) -> anyhow::Result<Vec<Record>> {
+    let mut reader = Reader::from_str(text);
+    let mut records = Vec::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) | Event::Empty(element) => {
+                let tag = String::from_utf8(element.name().as_ref().to_vec())?;
+                let mut attributes = HashMap::new();
+                for attr in element.attributes() {
+                    let attr = attr?;
+                    attributes.insert(
+                        String::from_utf8(attr.key.as_ref().to_vec())?,
+                        attr.unescape_value()?.into_owned(),
+                    );
+                }
+                records.push((tag, attributes));
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(records)
+}
+
+// PNGのアルファチャンネル (アルファが無い場合は赤) をグリフとして読み込む
+fn read_page(path: &Path, color_as_alpha: bool) -> anyhow::Result<BitmapPage> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+
+    let samples = info.color_type.samples();
+    let has_alpha = matches!(
+        info.color_type,
+        png::ColorType::GrayscaleAlpha | png::ColorType::Rgba
+    );
+    let channel = if has_alpha && !color_as_alpha {
+        samples - 1
+    } else {
+        0
+    };
+    Ok(BitmapPage {
+        width: info.width,
+        height: info.height,
+        alpha: buffer
+            .chunks_exact(samples)
+            .map(|pixel| pixel[channel])
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text() {
+        let text = r#"info face="Pixel Font" size=8 bold=0 italic=0
+common lineHeight=10 base=8 scaleW=16 scaleH=16 pages=1 packed=0 alphaChnl=4
+page id=0 file="pixel page_0.png"
+chars count=3
+char id=66   x=5 y=0 width=4 height=6 xoffset=0 yoffset=2 xadvance=5 page=0 chnl=15
+char id=65   x=0 y=0 width=4 height=6 xoffset=1 yoffset=2 xadvance=6 page=0 chnl=15
+char id=-1   x=9 y=0 width=4 height=6 xoffset=0 yoffset=2 xadvance=5 page=0 chnl=15
+kernings count=1
+kerning first=65 second=66 amount=-1
+"#;
+        let descriptor = parse_descriptor(text.as_bytes()).unwrap();
+        assert_eq!(descriptor.face, "Pixel Font");
+        assert_eq!((descriptor.line_height, descriptor.base), (10, 8));
+        assert!(descriptor.color_as_alpha);
+        assert_eq!(descriptor.page_files, vec!["pixel page_0.png"]);
+        // 文字順、id=-1は無視
+        assert_eq!(
+            descriptor.chars.iter().map(|g| g.c).collect::<Vec<_>>(),
+            vec!['A', 'B']
+        );
+        assert_eq!(
+            descriptor.chars[0],
+            BitmapChar {
+                c: 'A',
+                page: 0,
+                x: 0,
+                y: 0,
+                width: 4,
+                height: 6,
+                xoffset: 1,
+                yoffset: 2,
+                xadvance: 6,
+            }
+        );
+        assert_eq!(
+            descriptor.kernings,
+            vec![KerningPair {
+                first: 'A',
+                second: 'B',
+                amount: -1,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_xml() {
+        let xml = r#"<?xml version="1.0"?>
+<font>
+  <info face="Tom &amp; Jerry" size="8"/>
+  <common lineHeight="10" base="8" scaleW="16" scaleH="16" pages="1" packed="0" alphaChnl="0"/>
+  <pages><page id="0" file="test_0.png"/></pages>
+  <chars count="1">
+    <char id="12354" x="0" y="0" width="8" height="8" xoffset="0" yoffset="1" xadvance="9" page="0" chnl="15"/>
+  </chars>
+</font>
+"#;
+        let descriptor = parse_descriptor(xml.as_bytes()).unwrap();
+        assert_eq!(descriptor.face, "Tom & Jerry");
+        assert!(!descriptor.color_as_alpha);
+        assert_eq!(descriptor.chars[0].c, 'あ');
+        assert_eq!(descriptor.chars[0].xadvance, 9);
+
+        // バイナリ形式とpacked=1は未対応
+        assert!(parse_descriptor(b"BMF\x03").is_err());
+        let packed = xml.replace(r#"packed="0""#, r#"packed="1""#);
+        assert!(parse_descriptor(packed.as_bytes()).is_err());
+    }
+}
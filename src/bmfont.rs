@@ -0,0 +1,408 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use font_kit::source::Source;
+use quick_xml::{
+    events::{BytesDecl, Event},
+    Writer,
+};
+
+use crate::{
+    atlas::{load_font, points_to_pixels, GlyphAtlas},
+    import::group_regions,
+    kerning::kerning_pairs,
+    xml::{DynamicFont, FontStyle},
+};
+
+// BMFontのページの最大サイズ
+const BMFONT_PAGE_SIZE: u32 = 2048;
+
+// .fntの形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(unused)]
+pub enum BmFontFormat {
+    Text,
+    Xml,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KerningPair {
+    pub first: char,
+    pub second: char,
+    pub amount: i32,
+}
+
+// AngelCode BMFont (.fnt + PNGのページ)
+#[derive(Debug, Clone)]
+pub struct BmFont {
+    pub file_name: String,
+    pub face: String,
+    // ピクセル単位
+    pub size: i32,
+    pub style: FontStyle,
+    pub spacing: f32,
+    pub atlas: GlyphAtlas,
+    pub kernings: Vec<KerningPair>,
+}
+
+impl BmFont {
+    pub fn build(font: &DynamicFont, source: &dyn Source) -> anyhow::Result<Self> {
+        let atlas = GlyphAtlas::build(font, source, BMFONT_PAGE_SIZE)?;
+        let kernings = if font.use_kerning {
+            char_kerning_pairs(font, &atlas, source)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            file_name: font.file_name.clone(),
+            face: font.font_name.clone(),
+            size: points_to_pixels(font.size).round() as i32,
+            style: font.style,
+            spacing: font.spacing,
+            atlas,
+            kernings,
+        })
+    }
+
+    fn page_file(&self, page: usize) -> String {
+        format!("{}_{}.png", self.file_name, page)
+    }
+
+    fn is_bold(&self) -> bool {
+        matches!(self.style, FontStyle::Bold | FontStyle::BoldItalic)
+    }
+
+    fn is_italic(&self) -> bool {
+        matches!(self.style, FontStyle::Italic | FontStyle::BoldItalic)
+    }
+
+    // グリフの属性 (id, x, y, width, height, xoffset, yoffset, xadvance, page)
+    fn char_attributes(&self) -> Vec<[(&'static str, i64); 9]> {
+        self.atlas
+            .glyphs
+            .iter()
+            .map(|glyph| {
+                [
+                    ("id", glyph.c as i64),
+                    ("x", glyph.x as i64),
+                    ("y", glyph.y as i64),
+                    ("width", glyph.width as i64),
+                    ("height", glyph.height as i64),
+                    ("xoffset", glyph.left as i64),
+                    ("yoffset", glyph.top as i64),
+                    ("xadvance", (glyph.advance + self.spacing).round() as i64),
+                    ("page", glyph.page as i64),
+                ]
+            })
+            .collect()
+    }
+
+    // テキスト形式 (値の"はエスケープできないため削除)
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        writeln!(
+            text,
+            "info face=\"{}\" size={} bold={} italic={} charset=\"\" unicode=1 stretchH=100 smooth=1 aa=1 padding=0,0,0,0 spacing=1,1 outline=0",
+            self.face.replace('"', ""),
+            self.size,
+            self.is_bold() as u8,
+            self.is_italic() as u8
+        )
+        .ok();
+        writeln!(
+            text,
+            "common lineHeight={} base={} scaleW={} scaleH={} pages={} packed=0 alphaChnl=0 redChnl=4 greenChnl=4 blueChnl=4",
+            self.atlas.line_height,
+            self.atlas.base,
+            self.atlas.width,
+            self.atlas.height,
+            self.atlas.pages.len()
+        )
+        .ok();
+        for page in 0..self.atlas.pages.len() {
+            writeln!(
+                text,
+                "page id={} file=\"{}\"",
+                page,
+                self.page_file(page).replace('"', "")
+            )
+            .ok();
+        }
+        writeln!(text, "chars count={}", self.atlas.glyphs.len()).ok();
+        for attributes in self.char_attributes() {
+            let line = attributes
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(text, "char {} chnl=15", line).ok();
+        }
+        writeln!(text, "kernings count={}", self.kernings.len()).ok();
+        for pair in &self.kernings {
+            writeln!(
+                text,
+                "kerning first={} second={} amount={}",
+                pair.first as u32, pair.second as u32, pair.amount
+            )
+            .ok();
+        }
+        text
+    }
+
+    pub fn to_xml(&self) -> anyhow::Result<String> {
+        let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
+        writer
+            .create_element("font")
+            .write_inner_content(|writer| {
+                writer
+                    .create_element("info")
+                    .with_attributes([
+                        ("face", self.face.as_str()),
+                        ("size", &self.size.to_string()),
+                        ("bold", &(self.is_bold() as u8).to_string()),
+                        ("italic", &(self.is_italic() as u8).to_string()),
+                        ("charset", ""),
+                        ("unicode", "1"),
+                        ("stretchH", "100"),
+                        ("smooth", "1"),
+                        ("aa", "1"),
+                        ("padding", "0,0,0,0"),
+                        ("spacing", "1,1"),
+                        ("outline", "0"),
+                    ])
+                    .write_empty()?;
+                writer
+                    .create_element("common")
+                    .with_attributes([
+                        ("lineHeight", self.atlas.line_height.to_string().as_str()),
+                        ("base", &self.atlas.base.to_string()),
+                        ("scaleW", &self.atlas.width.to_string()),
+                        ("scaleH", &self.atlas.height.to_string()),
+                        ("pages", &self.atlas.pages.len().to_string()),
+                        ("packed", "0"),
+                        ("alphaChnl", "0"),
+                        ("redChnl", "4"),
+                        ("greenChnl", "4"),
+                        ("blueChnl", "4"),
+                    ])
+                    .write_empty()?;
+                writer
+                    .create_element("pages")
+                    .write_inner_content(|writer| {
+                        for page in 0..self.atlas.pages.len() {
+                            writer
+                                .create_element("page")
+                                .with_attributes([
+                                    ("id", page.to_string().as_str()),
+                                    ("file", &self.page_file(page)),
+                                ])
+                                .write_empty()?;
+                        }
+                        Ok(())
+                    })?;
+                writer
+                    .create_element("chars")
+                    .with_attribute(("count", self.atlas.glyphs.len().to_string().as_str()))
+                    .write_inner_content(|writer| {
+                        for attributes in self.char_attributes() {
+                            let values = attributes.map(|(name, value)| (name, value.to_string()));
+                            writer
+                                .create_element("char")
+                                .with_attributes(
+                                    values
+                                        .iter()
+                                        .map(|(name, value)| (*name, value.as_str()))
+                                        .chain([("chnl", "15")]),
+                                )
+                                .write_empty()?;
+                        }
+                        Ok(())
+                    })?;
+                writer
+                    .create_element("kernings")
+                    .with_attribute(("count", self.kernings.len().to_string().as_str()))
+                    .write_inner_content(|writer| {
+                        for pair in &self.kernings {
+                            writer
+                                .create_element("kerning")
+                                .with_attributes([
+                                    ("first", (pair.first as u32).to_string().as_str()),
+                                    ("second", &(pair.second as u32).to_string()),
+                                    ("amount", &pair.amount.to_string()),
+                                ])
+                                .write_empty()?;
+                        }
+                        Ok(())
+                    })?;
+                Ok(())
+            })?;
+
+        Ok(String::from_utf8(writer.into_inner())?)
+    }
+
+    // .fntとページのPNGを書き出す
+    pub fn write(&self, directory: &Path, format: BmFontFormat) -> anyhow::Result<Vec<PathBuf>> {
+        fs::create_dir_all(directory)?;
+        let content = match format {
+            BmFontFormat::Text => self.to_text(),
+            BmFontFormat::Xml => self.to_xml()?,
+        };
+        let path = directory.join(format!("{}.fnt", self.file_name));
+        fs::write(&path, content)?;
+
+        let mut paths = vec![path];
+        for (page, alpha) in self.atlas.pages.iter().enumerate() {
+            let path = directory.join(self.page_file(page));
+            write_png(&path, self.atlas.width, self.atlas.height, alpha)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+// 白色のグリフをアルファ値で描いたPNG
+fn write_png(path: &Path, width: u32, height: u32, alpha: &[u8]) -> anyhow::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let pixels = alpha
+        .iter()
+        .flat_map(|&a| [255, 255, 255, a])
+        .collect::<Vec<_>>();
+    encoder.write_header()?.write_image_data(&pixels)?;
+    Ok(())
+}
+
+// 同じフォント・サイズの文字の組み合わせのカーニング (ピクセル単位)
+fn char_kerning_pairs(
+    font: &DynamicFont,
+    atlas: &GlyphAtlas,
+    source: &dyn Source,
+) -> anyhow::Result<Vec<KerningPair>> {
+    let mut loaded = HashMap::new();
+    let mut kernings = Vec::new();
+    // ビットマップフォントは.fntのカーニングをそのまま使用
+    for bitmap_override in &font.bitmap_overrides {
+        kernings.extend(
+            bitmap_override
+                .font
+                .kernings
+                .iter()
+                .copied()
+                .filter(|pair| {
+                    [pair.first, pair.second].iter().all(|c| {
+                        bitmap_override.chars.contains(*c as u32) && atlas.glyph(*c).is_some()
+                    })
+                }),
+        );
+    }
+    for imported in group_regions(font) {
+        let loaded_font = load_font(&mut loaded, &imported.font_name, source)?;
+        let scale = points_to_pixels(imported.size.unwrap_or(font.size))
+            / loaded_font.metrics().units_per_em as f32;
+
+        // グリフIDから文字 (同じグリフの文字が複数ある場合も)
+        let mut glyph_chars: HashMap<u16, Vec<char>> = HashMap::new();
+        for c in imported.chars.code_points().filter_map(char::from_u32) {
+            if atlas.glyph(c).is_none() || font.bitmap_override(c).is_some() {
+                continue;
+            }
+            if let Some(glyph) = loaded_font.glyph_for_char(c) {
+                glyph_chars.entry(glyph as u16).or_default().push(c);
+            }
+        }
+        let glyphs = glyph_chars.keys().copied().collect::<HashSet<_>>();
+
+        for ((left, right), value) in kerning_pairs(loaded_font, &glyphs) {
+            let amount = (value as f32 * scale).round() as i32;
+            if amount == 0 {
+                continue;
+            }
+            for &first in &glyph_chars[&left] {
+                for &second in &glyph_chars[&right] {
+                    kernings.push(KerningPair {
+                        first,
+                        second,
+                        amount,
+                    });
+                }
+            }
+        }
+    }
+    kernings.sort_by_key(|pair| (pair.first, pair.second));
+    Ok(kernings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atlas::AtlasGlyph;
+
+    fn test_font() -> BmFont {
+        let glyph = |c, x, advance| AtlasGlyph {
+            c,
+            page: 0,
+            x,
+            y: 1,
+            width: 5,
+            height: 7,
+            left: 1,
+            top: 3,
+            advance,
+        };
+        BmFont {
+            file_name: "Test".to_string(),
+            face: "Tom & \"Jerry\"".to_string(),
+            size: 16,
+            style: FontStyle::Bold,
+            spacing: 1.0,
+            atlas: GlyphAtlas {
+                width: 64,
+                height: 64,
+                pages: vec![vec![0; 64 * 64]],
+                glyphs: vec![glyph('A', 1, 6.4), glyph('V', 7, 6.0)],
+                line_height: 19,
+                base: 15,
+                warnings: Vec::new(),
+            },
+            kernings: vec![KerningPair {
+                first: 'A',
+                second: 'V',
+                amount: -1,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_bmfont_text() {
+        let text = test_font().to_text();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert!(lines[0].starts_with("info face=\"Tom & Jerry\" size=16 bold=1 italic=0"));
+        assert!(lines[1].starts_with("common lineHeight=19 base=15 scaleW=64 scaleH=64 pages=1"));
+        assert_eq!(lines[2], "page id=0 file=\"Test_0.png\"");
+        assert_eq!(lines[3], "chars count=2");
+        // xadvanceはSpacingを含む
+        assert_eq!(
+            lines[4],
+            "char id=65 x=1 y=1 width=5 height=7 xoffset=1 yoffset=3 xadvance=7 page=0 chnl=15"
+        );
+        assert_eq!(lines[6], "kernings count=1");
+        assert_eq!(lines[7], "kerning first=65 second=86 amount=-1");
+    }
+
+    #[test]
+    fn test_bmfont_xml() {
+        let xml = test_font().to_xml().unwrap();
+        assert!(xml.contains(r#"<info face="Tom &amp; &quot;Jerry&quot;" size="16" bold="1""#));
+        assert!(xml.contains(r#"<page id="0" file="Test_0.png"/>"#));
+        assert!(xml.contains(r#"<char id="86" x="7" y="1" width="5" height="7" xoffset="1" yoffset="3" xadvance="7" page="0" chnl="15"/>"#));
+        assert!(xml.contains(r#"<kerning first="65" second="86" amount="-1"/>"#));
+    }
+}
@@ -0,0 +1,215 @@
+use std::{collections::HashSet, fs, path::PathBuf};
+
+use anyhow::Context as _;
+use serde::Serialize;
+
+use crate::{
+    charset::Preset,
+    filter::CharFilter,
+    types::{CharRange, CharRangeList, Counter},
+};
+
+// 同梱の優先順位: プリセットの順に残す (プリセット内はコードポイント順)
+const DEFAULT_RANKING: [Preset; 11] = [
+    Preset::Ascii,
+    Preset::Latin1,
+    Preset::Kana,
+    Preset::Joyo,
+    Preset::JisX0208Level1,
+    Preset::Gb2312,
+    Preset::Big5Common,
+    Preset::KsX1001Hangul,
+    Preset::JisX0208Level2,
+    Preset::Cyrillic,
+    Preset::Greek,
+];
+
+// 文字の優先順位
+#[derive(Debug, Clone)]
+pub enum Ranking {
+    Presets(Vec<Preset>),
+    // 頻度の高い順に1行1文字 (タブ以降は無視)
+    File(PathBuf),
+}
+
+impl Ranking {
+    fn chars(&self) -> anyhow::Result<Vec<u32>> {
+        match self {
+            Ranking::Presets(presets) => {
+                let mut chars = Vec::new();
+                for preset in presets {
+                    chars.extend(preset.chars().code_points());
+                }
+                Ok(chars)
+            }
+            Ranking::File(path) => {
+                let content = fs::read_to_string(path).with_context(|| {
+                    format!("Failed to read frequency list: {}", path.display())
+                })?;
+                Ok(content
+                    .lines()
+                    .filter(|line| !line.starts_with('#'))
+                    .filter_map(|line| line.trim_start().chars().next())
+                    .map(|c| c as u32)
+                    .collect())
+            }
+        }
+    }
+}
+
+// DynamicFontBuilder毎の最大文字数
+#[derive(Debug, Clone)]
+pub struct GlyphBudget {
+    max_chars: usize,
+    required: Vec<CharFilter>,
+    ranking: Ranking,
+}
+
+impl GlyphBudget {
+    #[allow(unused)]
+    pub fn new(max_chars: usize) -> Self {
+        Self {
+            max_chars,
+            required: Vec::new(),
+            ranking: Ranking::Presets(DEFAULT_RANKING.to_vec()),
+        }
+    }
+
+    // 上限を超えても必ず残す文字
+    #[allow(unused)]
+    pub fn require(mut self, filter: impl Into<CharFilter>) -> Self {
+        self.required.push(filter.into());
+        self
+    }
+
+    #[allow(unused)]
+    pub fn ranking(mut self, ranking: Ranking) -> Self {
+        self.ranking = ranking;
+        self
+    }
+
+    #[allow(unused)]
+    pub fn frequency_file<T: Into<PathBuf>>(self, path: T) -> Self {
+        self.ranking(Ranking::File(path.into()))
+    }
+
+    // 必須の文字、優先順位の高い文字、残りの文字 (コードポイント順) の順に上限まで残す
+    // must_keepはビルドに必須の文字 (DynamicFontBuilder::require等)
+    // 戻り値は除外した文字
+    pub fn apply(
+        &self,
+        chars: &CharRangeList,
+        default_character: char,
+        must_keep: &CharRangeList,
+    ) -> anyhow::Result<CharRangeList> {
+        if chars.count() <= self.max_chars {
+            return Ok(CharRangeList::new());
+        }
+
+        let mut kept = CharRangeList::new();
+        if chars.contains(default_character as u32) {
+            kept.add_range(CharRange::new_single(default_character as u32));
+        }
+        kept = kept.union(&must_keep.intersection(chars));
+        for filter in &self.required {
+            kept = kept.union(&filter.select(chars)?);
+        }
+        let mut kept_count = kept.count();
+        if kept_count > self.max_chars {
+            anyhow::bail!(
+                "Required characters ({}) exceed the glyph budget ({})",
+                kept_count,
+                self.max_chars
+            );
+        }
+
+        // 1文字ずつadd_rangeすると二乗になるので、集めてから一度に追加する
+        let ranked = self.ranking.chars()?;
+        let mut added = HashSet::new();
+        for c in ranked.into_iter().chain(chars.code_points()) {
+            if kept_count >= self.max_chars {
+                break;
+            }
+            if chars.contains(c) && !kept.contains(c) && added.insert(c) {
+                kept_count += 1;
+            }
+        }
+        kept.extend(added.into_iter().map(CharRange::new_single));
+
+        Ok(chars.difference(&kept))
+    }
+
+    pub fn max_chars(&self) -> usize {
+        self.max_chars
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BudgetReport {
+    #[serde(rename = "@MaxChars")]
+    pub max_chars: usize,
+    #[serde(rename = "@Dropped")]
+    pub dropped_count: usize,
+    #[serde(rename = "Dropped")]
+    pub dropped: CharRangeList,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_not_exceeded() {
+        let chars = CharRangeList::from(vec![CharRange::new(0x20, 0x7E)]);
+        let dropped = GlyphBudget::new(100)
+            .apply(&chars, '*', &CharRangeList::new())
+            .unwrap();
+        assert_eq!(dropped.count(), 0);
+    }
+
+    #[test]
+    fn test_budget_priority() {
+        // ASCII + ひらがな + 漢字
+        let chars = CharRangeList::from(vec![
+            CharRange::new(0x20, 0x7E),
+            CharRange::new(0x3041, 0x3096),
+            CharRange::new(0x4E00, 0x4E0F),
+        ]);
+        let budget = GlyphBudget::new(100)
+            .require(CharRange::new(0x4E00, 0x4E00))
+            .ranking(Ranking::Presets(vec![Preset::Kana, Preset::Ascii]));
+        let dropped = budget.apply(&chars, '*', &CharRangeList::new()).unwrap();
+
+        assert_eq!(dropped.count(), chars.count() - 100);
+        // 必須の文字と既定の文字は残る
+        assert!(!dropped.contains(0x4E00));
+        assert!(!dropped.contains('*' as u32));
+        // ひらがなはASCIIより優先される
+        assert!(!dropped.contains(0x3041));
+        assert!(dropped.contains(0x7E));
+        assert!(dropped.contains(0x4E01));
+    }
+
+    #[test]
+    fn test_budget_must_keep() {
+        // ランキングに無い漢字でも、ビルドに必須の文字は上限を超えても残す
+        let chars = CharRangeList::from(vec![
+            CharRange::new(0x20, 0x7E),
+            CharRange::new(0x4E00, 0x4E0F),
+        ]);
+        let must_keep = CharRangeList::from(vec![CharRange::new_single(0x4E0F)]);
+        let budget = GlyphBudget::new(20).ranking(Ranking::Presets(vec![Preset::Ascii]));
+        let dropped = budget.apply(&chars, '*', &must_keep).unwrap();
+
+        assert_eq!(dropped.count(), chars.count() - 20);
+        assert!(!dropped.contains(0x4E0F));
+        assert!(dropped.contains(0x4E00));
+    }
+
+    #[test]
+    fn test_budget_required_exceeded() {
+        let chars = CharRangeList::from(vec![CharRange::new(0x20, 0x7E)]);
+        let budget = GlyphBudget::new(10).require(Preset::Ascii);
+        assert!(budget.apply(&chars, '*', &CharRangeList::new()).is_err());
+    }
+}
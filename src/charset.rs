@@ -0,0 +1,230 @@
+use std::{fmt::Display, ops::RangeInclusive};
+
+use encoding_rs::{Encoding, BIG5, EUC_JP, EUC_KR, GBK};
+use serde::Serialize;
+use unicode_general_category::{get_general_category, GeneralCategory};
+
+use crate::types::{CharRange, CharRangeList, Counter};
+
+// 文字一覧ファイルは実行時の作業ディレクトリに依存しないよう埋め込む
+const JOYO_TXT: &str = include_str!("../charsets/joyo.txt");
+const JINMEIYO_TXT: &str = include_str!("../charsets/jinmeiyo.txt");
+
+// よく使う文字集合のプリセット
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Preset {
+    Ascii,
+    Latin1,
+    Kana,
+    JisX0208Level1,
+    JisX0208Level2,
+    Joyo,
+    Jinmeiyo,
+    Gb2312,
+    Big5Common,
+    KsX1001Hangul,
+    Cyrillic,
+    Greek,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 12] = [
+        Preset::Ascii,
+        Preset::Latin1,
+        Preset::Kana,
+        Preset::JisX0208Level1,
+        Preset::JisX0208Level2,
+        Preset::Joyo,
+        Preset::Jinmeiyo,
+        Preset::Gb2312,
+        Preset::Big5Common,
+        Preset::KsX1001Hangul,
+        Preset::Cyrillic,
+        Preset::Greek,
+    ];
+
+    pub fn chars(&self) -> CharRangeList {
+        match self {
+            Preset::Ascii => CharRangeList::from(vec![CharRange::new(0x20, 0x7E)]),
+            Preset::Latin1 => {
+                CharRangeList::from(vec![CharRange::new(0x20, 0x7E), CharRange::new(0xA0, 0xFF)])
+            }
+            Preset::Kana => CharRangeList::from(vec![
+                CharRange::new(0x3041, 0x3096),
+                CharRange::new(0x3099, 0x30FF),
+            ]),
+            // 第1水準: 16-47区, 第2水準: 48-84区
+            Preset::JisX0208Level1 => decode_double_byte(EUC_JP, 0xB0..=0xCF, &[0xA1..=0xFE]),
+            Preset::JisX0208Level2 => decode_double_byte(EUC_JP, 0xD0..=0xF4, &[0xA1..=0xFE]),
+            Preset::Joyo => CharRangeList::from_sample_text(JOYO_TXT),
+            Preset::Jinmeiyo => CharRangeList::from_sample_text(JINMEIYO_TXT),
+            // 漢字の第1・第2水準: 0xB0A1-0xF7FE
+            Preset::Gb2312 => decode_double_byte(GBK, 0xB0..=0xF7, &[0xA1..=0xFE]),
+            // 常用字: 0xA440-0xC67E
+            Preset::Big5Common => {
+                decode_double_byte(BIG5, 0xA4..=0xC5, &[0x40..=0x7E, 0xA1..=0xFE])
+                    .union(&decode_double_byte(BIG5, 0xC6..=0xC6, &[0x40..=0x7E]))
+            }
+            // ハングル: 16-40区
+            Preset::KsX1001Hangul => decode_double_byte(EUC_KR, 0xB0..=0xC8, &[0xA1..=0xFE]),
+            Preset::Cyrillic => assigned_chars(CharRange::new(0x0400, 0x04FF)),
+            Preset::Greek => assigned_chars(CharRange::new(0x0370, 0x03FF)),
+        }
+    }
+}
+
+impl Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Preset::Ascii => "ASCII",
+            Preset::Latin1 => "Latin-1",
+            Preset::Kana => "Kana",
+            Preset::JisX0208Level1 => "JIS X 0208 Level 1",
+            Preset::JisX0208Level2 => "JIS X 0208 Level 2",
+            Preset::Joyo => "Joyo Kanji",
+            Preset::Jinmeiyo => "Jinmeiyo Kanji",
+            Preset::Gb2312 => "GB2312",
+            Preset::Big5Common => "Big5 Common",
+            Preset::KsX1001Hangul => "KS X 1001 Hangul",
+            Preset::Cyrillic => "Cyrillic",
+            Preset::Greek => "Greek",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// 2バイトエンコーディングの表から文字を取り出す
+// 漢字・ハングルの表のみを対象にしているため、Lo以外(外字等)は除外する
+fn decode_double_byte(
+    encoding: &'static Encoding,
+    lead: RangeInclusive<u8>,
+    trails: &[RangeInclusive<u8>],
+) -> CharRangeList {
+    let mut chars = CharRangeList::new();
+    for lead in lead {
+        for trail in trails.iter().flat_map(|trail| trail.clone()) {
+            let bytes = [lead, trail];
+            let Some(decoded) =
+                encoding.decode_without_bom_handling_and_without_replacement(&bytes)
+            else {
+                continue;
+            };
+            let mut decoded = decoded.chars();
+            if let (Some(c), None) = (decoded.next(), decoded.next()) {
+                if get_general_category(c) == GeneralCategory::OtherLetter {
+                    chars.add_range(CharRange::new_single(c as u32));
+                }
+            }
+        }
+    }
+    chars
+}
+
+fn assigned_chars(range: CharRange) -> CharRangeList {
+    let mut chars = CharRangeList::new();
+    for c in (range.start..=range.end).filter_map(char::from_u32) {
+        if get_general_category(c) != GeneralCategory::Unassigned {
+            chars.add_range(CharRange::new_single(c as u32));
+        }
+    }
+    chars
+}
+
+// プリセットの文字をどれだけ含んでいるか
+#[derive(Debug, Clone, Serialize)]
+pub struct PresetCoverage {
+    #[serde(rename = "@Preset")]
+    pub preset: Preset,
+    #[serde(rename = "@Covered")]
+    pub covered: usize,
+    #[serde(rename = "@Total")]
+    pub total: usize,
+}
+
+impl PresetCoverage {
+    pub fn compute(preset: Preset, chars: &CharRangeList) -> Self {
+        let preset_chars = preset.chars();
+        Self {
+            preset,
+            covered: preset_chars.intersection(chars).count(),
+            total: preset_chars.count(),
+        }
+    }
+
+    pub fn compute_all(chars: &CharRangeList) -> Vec<Self> {
+        Preset::ALL
+            .iter()
+            .map(|preset| Self::compute(*preset, chars))
+            .collect()
+    }
+
+    pub fn ratio(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.covered as f64 / self.total as f64
+        }
+    }
+}
+
+impl Display for PresetCoverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}/{} ({:.1}%)",
+            self.preset,
+            self.covered,
+            self.total,
+            self.ratio() * 100.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preset_counts() {
+        let expected = [
+            (Preset::Ascii, 95),
+            (Preset::Latin1, 191),
+            (Preset::JisX0208Level1, 2965),
+            (Preset::JisX0208Level2, 3390),
+            (Preset::Joyo, 2136),
+            (Preset::Jinmeiyo, 863),
+            (Preset::Gb2312, 6763),
+            (Preset::Big5Common, 5401),
+            (Preset::KsX1001Hangul, 2350),
+        ];
+        for (preset, count) in expected {
+            assert_eq!(preset.chars().count(), count, "{}", preset);
+        }
+    }
+
+    #[test]
+    fn test_preset_contents() {
+        let jis1 = Preset::JisX0208Level1.chars();
+        assert!(jis1.contains('亜' as u32));
+        assert!(!jis1.contains('弌' as u32));
+        assert!(Preset::JisX0208Level2.chars().contains('弌' as u32));
+        assert!(Preset::KsX1001Hangul.chars().contains('가' as u32));
+        assert!(Preset::Greek.chars().contains('Ω' as u32));
+        assert!(Preset::Cyrillic.chars().contains('Ж' as u32));
+
+        // 人名用漢字は常用漢字と重ならない
+        let joyo = Preset::Joyo.chars();
+        let jinmeiyo = Preset::Jinmeiyo.chars();
+        assert_eq!(joyo.intersection(&jinmeiyo).count(), 0);
+        assert!(jinmeiyo.contains('凜' as u32));
+        assert!(jinmeiyo.contains('櫻' as u32));
+    }
+
+    #[test]
+    fn test_preset_coverage() {
+        let chars = CharRangeList::from(vec![CharRange::new(0x20, 0x4F)]);
+        let coverage = PresetCoverage::compute(Preset::Ascii, &chars);
+        assert_eq!(coverage.covered, 48);
+        assert_eq!(coverage.total, 95);
+    }
+}
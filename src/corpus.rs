@@ -0,0 +1,406 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use serde::Serialize;
+
+use crate::{
+    charset::Preset,
+    types::{CharRange, CharRangeList},
+};
+
+// この回数以下しか使われていない文字をレポートに記録する
+const RARE_CHAR_THRESHOLD: usize = 2;
+
+// ローカライズファイルから使用されている文字を収集する
+#[derive(Debug, Clone, Default)]
+pub struct Corpus {
+    paths: Vec<PathBuf>,
+    margin: Vec<Preset>,
+}
+
+impl Corpus {
+    #[allow(unused)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // .hjson/.csvファイル、またはそれらを含むディレクトリを追加
+    #[allow(unused)]
+    pub fn add_path<T: Into<PathBuf>>(mut self, path: T) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    // コーパスに含まれていなくても常に含める文字集合
+    #[allow(unused)]
+    pub fn margin(mut self, preset: Preset) -> Self {
+        self.margin.push(preset);
+        self
+    }
+
+    pub fn scan(&self) -> anyhow::Result<CorpusChars> {
+        let mut corpus_chars = CorpusChars::default();
+
+        for path in &self.paths {
+            for file in collect_files(path)? {
+                let content = fs::read_to_string(&file)
+                    .with_context(|| format!("Failed to read: {}", file.display()))?;
+                let texts = match extension(&file).as_str() {
+                    "hjson" => extract_hjson_texts(&content),
+                    "csv" => extract_csv_texts(&content),
+                    _ => continue,
+                };
+                for (line, text) in texts {
+                    corpus_chars.add_text(&text, &file, line);
+                }
+            }
+        }
+
+        for preset in &self.margin {
+            corpus_chars.margin.extend(preset.chars());
+        }
+
+        Ok(corpus_chars)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CorpusChars {
+    // コーパスで使用されている文字
+    pub used: CharRangeList,
+    // 安全のために追加する文字
+    pub margin: CharRangeList,
+    occurrences: BTreeMap<u32, CharOccurrence>,
+}
+
+#[derive(Debug, Clone)]
+struct CharOccurrence {
+    count: usize,
+    file: PathBuf,
+    line: usize,
+}
+
+impl CorpusChars {
+    fn add_text(&mut self, text: &str, file: &Path, line: usize) {
+        for c in text.chars().filter(|c| !c.is_control()) {
+            let occurrence = self
+                .occurrences
+                .entry(c as u32)
+                .or_insert_with(|| CharOccurrence {
+                    count: 0,
+                    file: file.to_path_buf(),
+                    line,
+                });
+            occurrence.count += 1;
+            if occurrence.count == 1 {
+                self.used.add_range(CharRange::new_single(c as u32));
+            }
+        }
+    }
+
+    // 使用されている文字と安全マージンの和集合
+    pub fn chars(&self) -> CharRangeList {
+        self.used.union(&self.margin)
+    }
+
+    pub fn rare_chars(&self) -> Vec<RareChar> {
+        self.occurrences
+            .iter()
+            .filter(|(_, occurrence)| occurrence.count <= RARE_CHAR_THRESHOLD)
+            .filter(|(c, _)| !self.margin.contains(**c))
+            .map(|(c, occurrence)| RareChar {
+                char: char::from_u32(*c).unwrap_or_default(),
+                code: format!("U+{:04X}", c),
+                count: occurrence.count,
+                file: occurrence.file.display().to_string(),
+                line: occurrence.line,
+            })
+            .collect()
+    }
+}
+
+// 使用回数の少ない文字と、最初に使用されたファイル
+#[derive(Debug, Clone, Serialize)]
+pub struct RareChar {
+    #[serde(rename = "@Char")]
+    pub char: char,
+    #[serde(rename = "@Code")]
+    pub code: String,
+    #[serde(rename = "@Count")]
+    pub count: usize,
+    #[serde(rename = "@File")]
+    pub file: String,
+    #[serde(rename = "@Line")]
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CorpusReport {
+    #[serde(rename = "@Chars")]
+    pub chars: usize,
+    // コーパスで使用されているが、どのフォントにも含まれない文字
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub uncovered: Vec<String>,
+    #[serde(rename = "Rare")]
+    pub rare: Vec<RareChar>,
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn collect_files(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if !path.is_dir() {
+        anyhow::bail!("Localization path not found: {}", path.display());
+    }
+
+    let mut files = Vec::new();
+    for ext in ["hjson", "csv"] {
+        let query = path.join(format!("**/*.{}", ext));
+        for file in glob::glob(&query.to_string_lossy())? {
+            files.push(file?);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+// hjsonから値の文字列を取り出す (キーとコメントは除外)
+// 戻り値は (行番号, 文字列)
+fn extract_hjson_texts(content: &str) -> Vec<(usize, String)> {
+    let mut texts = Vec::new();
+    let mut in_block_comment = false;
+    let mut multiline: Option<(usize, String)> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+
+        // '''で囲まれた複数行文字列
+        if let Some((start, mut text)) = multiline.take() {
+            match line.find("'''") {
+                Some(end) => {
+                    text.push_str(&line[..end]);
+                    texts.push((start, text));
+                }
+                None => {
+                    text.push_str(line);
+                    text.push('\n');
+                    multiline = Some((start, text));
+                }
+            }
+            continue;
+        }
+
+        let mut rest = line.trim();
+        if in_block_comment {
+            match rest.find("*/") {
+                Some(end) => {
+                    in_block_comment = false;
+                    rest = rest[end + 2..].trim();
+                }
+                None => continue,
+            }
+        }
+        if rest.starts_with('#') || rest.starts_with("//") {
+            continue;
+        }
+        if let Some(body) = rest.strip_prefix("/*") {
+            match body.find("*/") {
+                Some(end) => rest = body[end + 2..].trim(),
+                None => {
+                    in_block_comment = true;
+                    continue;
+                }
+            }
+        }
+
+        // キーを除外
+        let value = match split_key(rest) {
+            Some(value) => value.trim(),
+            None => rest,
+        };
+        if value.is_empty() || matches!(value, "{" | "}" | "[" | "]" | "," | "{}" | "[]") {
+            continue;
+        }
+
+        if let Some(body) = value.strip_prefix("'''") {
+            match body.find("'''") {
+                Some(end) => texts.push((line_number, body[..end].to_string())),
+                None => multiline = Some((line_number, format!("{}\n", body))),
+            }
+        } else if let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') {
+            texts.push((line_number, unquote(&value[1..], quote)));
+        } else {
+            // クォートなし文字列は行末までが値
+            texts.push((line_number, value.trim_end_matches(',').to_string()));
+        }
+    }
+
+    texts
+}
+
+// `key: value` の value 部分を返す
+fn split_key(line: &str) -> Option<&str> {
+    let first = line.chars().next()?;
+    if first == '"' || first == '\'' {
+        let end = line[1..].find(first)? + 1;
+        return line[end + 1..].trim_start().strip_prefix(':');
+    }
+    let colon = line.find(':')?;
+    let key = &line[..colon];
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+    Some(&line[colon + 1..])
+}
+
+fn unquote(body: &str, quote: char) -> String {
+    let mut text = String::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c == quote => break,
+            '\\' => match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                        text.push(c);
+                    }
+                }
+                Some(c) => text.push(c),
+                None => break,
+            },
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+// csvからキー(1列目)以外のセルを取り出す
+fn extract_csv_texts(content: &str) -> Vec<(usize, String)> {
+    let mut texts = Vec::new();
+    let mut line_number = 1;
+    let mut cell = String::new();
+    let mut cell_line = 1;
+    let mut column = 0;
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+
+    let mut push_cell = |cell: &mut String, column: usize, line: usize| {
+        if column > 0 && !cell.is_empty() {
+            texts.push((line, std::mem::take(cell)));
+        }
+        cell.clear();
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                push_cell(&mut cell, column, cell_line);
+                column += 1;
+                cell_line = line_number;
+            }
+            '\n' if !in_quotes => {
+                push_cell(&mut cell, column, cell_line);
+                column = 0;
+                line_number += 1;
+                cell_line = line_number;
+            }
+            '\r' if !in_quotes => {}
+            c => {
+                if c == '\n' {
+                    line_number += 1;
+                }
+                cell.push(c);
+            }
+        }
+    }
+    push_cell(&mut cell, column, cell_line);
+
+    texts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Counter;
+
+    #[test]
+    fn test_extract_hjson_texts() {
+        let content = r#"
+# コメント
+Mods: {
+    ExternalLocalizer: {
+        // コメント
+        Name: 外部ローカライザー
+        "Quoted Key": "引用あ"
+        Multi:
+            '''
+            一行目
+            二行目
+            '''
+        /* ブロック
+           コメント */
+        Empty: ""
+    }
+}
+"#;
+        let texts: Vec<String> = extract_hjson_texts(content)
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect();
+        assert_eq!(texts[0], "外部ローカライザー");
+        assert_eq!(texts[1], "引用あ");
+        assert!(texts
+            .iter()
+            .any(|t| t.contains("一行目") && t.contains("二行目")));
+        assert!(!texts.iter().any(|t| t.contains("コメント")));
+    }
+
+    #[test]
+    fn test_extract_csv_texts() {
+        let content = "Key,Translation\nItem.Name,\"剣, 盾\"\nItem.Tooltip,\"一行目\n二行目\"\n";
+        let texts = extract_csv_texts(content);
+        assert_eq!(
+            texts,
+            vec![
+                (1, "Translation".to_string()),
+                (2, "剣, 盾".to_string()),
+                (3, "一行目\n二行目".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rare_chars() {
+        let mut corpus = CorpusChars::default();
+        corpus.add_text("ああああ鬱", Path::new("a.hjson"), 1);
+        corpus.add_text("あ鬱", Path::new("b.hjson"), 5);
+        corpus.add_text("龘", Path::new("b.hjson"), 7);
+
+        let rare = corpus.rare_chars();
+        assert_eq!(rare.len(), 2);
+        assert_eq!(rare[0].char, '鬱');
+        assert_eq!(rare[0].file, "a.hjson");
+        assert_eq!(rare[1].char, '龘');
+        assert_eq!(rare[1].line, 7);
+        assert_eq!(corpus.used.count(), 3);
+    }
+}
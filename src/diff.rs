@@ -0,0 +1,471 @@
+use std::{fmt::Display, fs, path::Path, process::Command};
+
+use anyhow::Context as _;
+use serde::Deserialize;
+
+use crate::{
+    explain::group_by_block,
+    types::{CharRange, CharRangeList, Counter},
+    xml::XnaContent,
+};
+
+// フォント毎に使用する文字 (フォールバックの順)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Assignments {
+    pub fonts: Vec<(String, CharRangeList)>,
+}
+
+impl Assignments {
+    fn add(&mut self, font_name: &str, chars: CharRangeList) {
+        match self.fonts.iter_mut().find(|(name, _)| name == font_name) {
+            Some((_, existing)) => *existing = existing.union(&chars),
+            None => self.fonts.push((font_name.to_string(), chars)),
+        }
+    }
+
+    fn get(&self, font_name: &str) -> CharRangeList {
+        self.fonts
+            .iter()
+            .find(|(name, _)| name == font_name)
+            .map(|(_, chars)| chars.clone())
+            .unwrap_or_default()
+    }
+
+    fn all_chars(&self) -> CharRangeList {
+        self.fonts
+            .iter()
+            .fold(CharRangeList::new(), |all, (_, chars)| all.union(chars))
+    }
+}
+
+// report.xmlの読み込みに必要な部分
+#[derive(Deserialize)]
+struct ReportFile {
+    #[serde(rename = "Font", default)]
+    fonts: Vec<ReportFont>,
+}
+
+#[derive(Deserialize)]
+struct ReportFont {
+    #[serde(rename = "@FileName")]
+    file_name: String,
+    #[serde(rename = "Assignment", default)]
+    assignments: Vec<ReportAssignment>,
+}
+
+#[derive(Deserialize)]
+struct ReportAssignment {
+    #[serde(rename = "@FontName")]
+    font_name: String,
+    // 文字集合の表記
+    #[serde(rename = "Chars", default)]
+    chars: String,
+}
+
+fn parse_dynamic_font(content: &str) -> anyhow::Result<Assignments> {
+    let font = XnaContent::from_xml(content)?.asset.font;
+    let mut assignments = Assignments::default();
+    for region in font.character_regions.character_region {
+        let range = CharRange::new(region.start as u32, region.end as u32);
+        // FontNameが無いCharacterRegionは基準のフォント
+        let font_name = region.font_name.as_deref().unwrap_or(&font.font_name);
+        assignments.add(font_name, CharRangeList::from(vec![range]));
+    }
+    Ok(assignments)
+}
+
+fn parse_report(content: &str) -> anyhow::Result<Vec<(String, Assignments)>> {
+    let report: ReportFile = quick_xml::de::from_str(content)?;
+    report
+        .fonts
+        .into_iter()
+        .map(|font| {
+            let mut assignments = Assignments::default();
+            for assignment in font.assignments {
+                let chars = assignment.chars.parse().with_context(|| {
+                    format!(
+                        "{}: Invalid characters of {}",
+                        font.file_name, assignment.font_name
+                    )
+                })?;
+                assignments.add(&assignment.font_name, chars);
+            }
+            Ok((font.file_name, assignments))
+        })
+        .collect()
+}
+
+// 比較対象の読み込み
+// report.xml、.dynamicfont、またはそれらを含むディレクトリ
+pub fn load(path: &Path) -> anyhow::Result<Vec<(String, Assignments)>> {
+    if path.is_dir() {
+        let query = path.join("**/*.dynamicfont").to_string_lossy().to_string();
+        let mut files = Vec::new();
+        for file in glob::glob(&query)? {
+            let file = file?;
+            let name = file.strip_prefix(path)?.with_extension("");
+            files.push((name.to_string_lossy().replace('\\', "/"), file));
+        }
+        files.sort();
+        return files
+            .into_iter()
+            .map(|(name, file)| Ok((name, load_dynamic_font(&file)?)))
+            .collect();
+    }
+
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("dynamicfont") => {
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            Ok(vec![(name, load_dynamic_font(path)?)])
+        }
+        Some("xml") => parse_report(&content)
+            .with_context(|| format!("Failed to parse report: {}", path.display())),
+        _ => anyhow::bail!(
+            "Unsupported file: {} (expected report.xml, .dynamicfont or a directory)",
+            path.display()
+        ),
+    }
+}
+
+fn load_dynamic_font(path: &Path) -> anyhow::Result<Assignments> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_dynamic_font(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+// フォント毎の変更
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontDiff {
+    pub font_name: String,
+    // 新たに含まれるようになった文字
+    pub added: CharRangeList,
+    // どのフォントからも使用されなくなった文字
+    pub removed: CharRangeList,
+    // 他のフォントから移ってきた文字 (移動元のフォント名)
+    pub reassigned: Vec<(String, CharRangeList)>,
+}
+
+impl FontDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.reassigned.is_empty()
+    }
+}
+
+pub fn diff(old: &Assignments, new: &Assignments) -> Vec<FontDiff> {
+    let (old_all, new_all) = (old.all_chars(), new.all_chars());
+
+    // 新しい方の順に、削除されたフォントを後に並べる
+    let mut font_names: Vec<&str> = new.fonts.iter().map(|(name, _)| name.as_str()).collect();
+    for (name, _) in &old.fonts {
+        if !font_names.contains(&name.as_str()) {
+            font_names.push(name);
+        }
+    }
+
+    font_names
+        .into_iter()
+        .map(|font_name| {
+            let (old_chars, new_chars) = (old.get(font_name), new.get(font_name));
+            let reassigned = old
+                .fonts
+                .iter()
+                .filter(|(name, _)| name != font_name)
+                .map(|(name, chars)| (name.clone(), new_chars.intersection(chars)))
+                .filter(|(_, chars)| !chars.is_empty())
+                .collect();
+            FontDiff {
+                font_name: font_name.to_string(),
+                added: new_chars.difference(&old_all),
+                removed: old_chars.difference(&new_all),
+                reassigned,
+            }
+        })
+        .filter(|font_diff| !font_diff.is_empty())
+        .collect()
+}
+
+impl Display for FontDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "  {}:", self.font_name)?;
+        let mut write_blocks = |sign: &str, chars: &CharRangeList, suffix: &str| {
+            for (block, chars) in group_by_block(chars) {
+                writeln!(
+                    f,
+                    "    {} {} ({}): {}{}",
+                    sign,
+                    block,
+                    chars.count(),
+                    chars,
+                    suffix
+                )?;
+            }
+            Ok(())
+        };
+        write_blocks("+", &self.added, "")?;
+        write_blocks("-", &self.removed, "")?;
+        for (from, chars) in &self.reassigned {
+            write_blocks("~", chars, &format!(" (from {})", from))?;
+        }
+        Ok(())
+    }
+}
+
+// 2つの読み込み結果を比較して表示 (変更が無ければfalse)
+pub fn print_diff(old: &[(String, Assignments)], new: &[(String, Assignments)]) -> bool {
+    let empty = Assignments::default();
+    let mut names: Vec<&str> = new.iter().map(|(name, _)| name.as_str()).collect();
+    for (name, _) in old {
+        if !names.contains(&name.as_str()) {
+            names.push(name);
+        }
+    }
+
+    let find = |list: &'_ [(String, Assignments)], name: &str| {
+        list.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, assignments)| assignments.clone())
+    };
+
+    let mut changed = false;
+    for name in names {
+        let (old_font, new_font) = (find(old, name), find(new, name));
+        let status = match (&old_font, &new_font) {
+            (None, Some(_)) => " (new)",
+            (Some(_), None) => " (deleted)",
+            _ => "",
+        };
+        let font_diffs = diff(
+            old_font.as_ref().unwrap_or(&empty),
+            new_font.as_ref().unwrap_or(&empty),
+        );
+        if font_diffs.is_empty() {
+            continue;
+        }
+        changed = true;
+        println!("{}{}:", name, status);
+        for font_diff in font_diffs {
+            print!("{}", font_diff);
+        }
+    }
+    changed
+}
+
+// git refの時点のツリー全体をビルドし、.dynamicfontをoutput_dirに書き出す
+// vendor/やcharsets/もgit refの時点のものを使用する
+pub fn generate_at_ref(git_ref: &str, output_dir: &Path) -> anyhow::Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let worktree = std::env::temp_dir().join(format!("font-diff-src-{}", std::process::id()));
+    let worktree_arg = worktree.to_string_lossy().to_string();
+    run_git(&["worktree", "add", "--detach", &worktree_arg, git_ref])
+        .with_context(|| format!("Failed to check out {}", git_ref))?;
+
+    let result = (|| -> anyhow::Result<()> {
+        // Cargo.lockはコミットされていないため、現在の依存関係のバージョンを使用
+        if Path::new("Cargo.lock").exists() && !worktree.join("Cargo.lock").exists() {
+            fs::copy("Cargo.lock", worktree.join("Cargo.lock"))?;
+        }
+        // fonts/はコミットされていないため作成 (古いコードはfonts/fonts.ymlを書き出す)
+        fs::create_dir_all(worktree.join("fonts"))?;
+        let cargo_run = |args: &[&str]| {
+            let mut command = Command::new("cargo");
+            command
+                .current_dir(&worktree)
+                .arg("run")
+                .arg("--quiet")
+                .arg("--target-dir")
+                .arg(current_dir.join("target").join("diff"))
+                .arg("--")
+                .args(args);
+            command
+        };
+
+        // 古いバイナリの使い方の表示からgenerateコマンドの有無を判定
+        let probe = cargo_run(&["help"]).output()?;
+        let probe_usage = format!(
+            "{}{}",
+            String::from_utf8_lossy(&probe.stdout),
+            String::from_utf8_lossy(&probe.stderr)
+        );
+        let fallback = match available_commands(&probe_usage) {
+            Some(commands) if commands.contains(&"generate") => {
+                let output_dir = current_dir.join(output_dir);
+                let status = cargo_run(&["generate", &output_dir.to_string_lossy()]).status()?;
+                if !status.success() {
+                    anyhow::bail!("Failed to build fonts at {} ({})", git_ref, status);
+                }
+                return Ok(());
+            }
+            Some(_) => {
+                println!(
+                    "{} has no generate command, using .dynamicfont files written before DynamicFontGenerator.exe",
+                    git_ref
+                );
+                cargo_run(&["build"]).output()?
+            }
+            // コマンドが無いバイナリは引数を無視してビルドするため、判定時の実行結果を使用
+            None => {
+                println!(
+                    "{} has no commands, using .dynamicfont files written before DynamicFontGenerator.exe",
+                    git_ref
+                );
+                probe
+            }
+        };
+        print!("{}", String::from_utf8_lossy(&fallback.stdout));
+        eprint!("{}", String::from_utf8_lossy(&fallback.stderr));
+
+        // DynamicFontGenerator.exeが無い場合は失敗するため、.dynamicfontを全て書き出した後であれば使用
+        let stdout = String::from_utf8_lossy(&fallback.stdout);
+        if !fallback.status.success() && !stdout.contains(DYNAMIC_FONTS_WRITTEN) {
+            anyhow::bail!(
+                "Failed to build fonts at {} before writing .dynamicfont files ({})",
+                git_ref,
+                fallback.status
+            );
+        }
+        let dyn_font_dir = worktree.join("fonts").join("dynamic");
+        if !dyn_font_dir.is_dir() {
+            anyhow::bail!("{} did not write {}", git_ref, dyn_font_dir.display());
+        }
+        copy_dir(&dyn_font_dir, &current_dir.join(output_dir))?;
+        Ok(())
+    })();
+
+    run_git(&["worktree", "remove", "--force", &worktree_arg]).ok();
+    result
+}
+
+// buildコマンドが全ての.dynamicfontを書き出した後に表示するメッセージ
+const DYNAMIC_FONTS_WRITTEN: &str = "Executing DynamicFontGenerator.exe...";
+
+// 使い方の表示の"Available commands: build, generate, ..."からコマンドの一覧を取得
+fn available_commands(usage: &str) -> Option<Vec<&str>> {
+    let (_, commands) = usage
+        .lines()
+        .find_map(|line| line.split_once("Available commands:"))?;
+    Some(commands.split(',').map(str::trim).collect())
+}
+
+fn run_git(args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> anyhow::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let path = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &path)?;
+        } else {
+            fs::copy(entry.path(), path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assignments(fonts: &[(&str, &str)]) -> Assignments {
+        Assignments {
+            fonts: fonts
+                .iter()
+                .map(|(name, chars)| (name.to_string(), chars.parse().unwrap()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = assignments(&[
+            ("Latin", "U+0020-007E, U+3041-3096"),
+            ("Japanese", "U+4E00"),
+        ]);
+        let new = assignments(&[
+            ("Latin", "U+0020-007E, U+00A0-00FF"),
+            ("Japanese", "U+3041-3096"),
+        ]);
+        let diffs = diff(&old, &new);
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].font_name, "Latin");
+        assert_eq!(diffs[0].added.to_string(), "U+00A0-00FF");
+        assert!(diffs[0].removed.is_empty());
+        assert_eq!(diffs[1].font_name, "Japanese");
+        assert_eq!(diffs[1].removed.to_string(), "一");
+        assert_eq!(diffs[1].reassigned[0].0, "Latin");
+        assert_eq!(diffs[1].reassigned[0].1.to_string(), "U+3041-3096");
+
+        assert!(diff(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_parse_dynamic_font() {
+        let content = r#"<?xml version="1.0" encoding="utf-8"?>
+<XnaContent xmlns:Graphics="ReLogic.Content.Pipeline">
+  <Asset Type="Graphics:DynamicFontDescription">
+    <FontName>Latin</FontName>
+    <Size>16</Size>
+    <CharacterRegions>
+      <CharacterRegion>
+        <Start>&#x20;</Start>
+        <End>&#x7E;</End>
+      </CharacterRegion>
+      <CharacterRegion>
+        <FontName>Japanese</FontName>
+        <Start>&#x3041;</Start>
+        <End>&#x3096;</End>
+      </CharacterRegion>
+    </CharacterRegions>
+  </Asset>
+</XnaContent>"#;
+        let parsed = parse_dynamic_font(content).unwrap();
+        assert_eq!(
+            parsed,
+            assignments(&[("Latin", "U+0020-007E"), ("Japanese", "U+3041-3096")])
+        );
+    }
+
+    #[test]
+    fn test_parse_report() {
+        let content = r#"<BuildReport>
+  <Font FileName="Test">
+    <License FontName="Latin"/>
+    <Assignment FontName="Latin">
+      <Chars>U+0020-007E</Chars>
+    </Assignment>
+  </Font>
+</BuildReport>"#;
+        let parsed = parse_report(content).unwrap();
+        assert_eq!(parsed[0].0, "Test");
+        assert_eq!(parsed[0].1, assignments(&[("Latin", "U+0020-007E")]));
+    }
+
+    #[test]
+    fn test_available_commands() {
+        let usage = "Available commands: build, generate, vendor, explain, diff, import\n";
+        assert!(available_commands(usage).unwrap().contains(&"generate"));
+
+        // generateコマンドが無い時点のエラー
+        let usage = "Error: Unknown command: help\nAvailable commands: build, vendor, explain\n";
+        assert!(!available_commands(usage).unwrap().contains(&"generate"));
+
+        // コマンドが無い時点はそのままビルドする
+        let usage = "Generating .dynamicfont files...\n";
+        assert_eq!(available_commands(usage), None);
+    }
+}
@@ -0,0 +1,311 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+use unicode_blocks::find_unicode_block;
+
+use crate::{
+    types::{CharRange, CharRangeList, Counter},
+    xml::{CharacterRegion, DynamicFont},
+};
+
+// フォールバック毎の文字の割り当て
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FontAttribution {
+    #[serde(rename = "@FontName")]
+    pub font_name: String,
+    // cmapに含まれる文字
+    #[serde(skip)]
+    pub cmap: CharRangeList,
+    // フィルター・コーパス・除外を適用した後の文字
+    #[serde(skip)]
+    pub candidates: CharRangeList,
+    // このフォントから使用する文字
+    #[serde(rename = "Chars")]
+    pub assigned: CharRangeList,
+}
+
+// ある文字に対するフォールバックの状態
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FontStatus {
+    // この文字を提供する
+    Supplied,
+    // 含んでいるが、前のフォントまたはpreferに負けた
+    Lost,
+    // 含んでいるが、どのフォントからも使用されない (文字数の上限等)
+    Dropped,
+    // cmapには含まれるが、フィルター等で除外した
+    Filtered,
+}
+
+impl Display for FontStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FontStatus::Supplied => write!(f, "supplied"),
+            FontStatus::Lost => write!(f, "lost in the cascade"),
+            FontStatus::Dropped => write!(f, "dropped"),
+            FontStatus::Filtered => write!(f, "filtered out"),
+        }
+    }
+}
+
+pub struct CharExplanation {
+    pub c: u32,
+    // 文字を含むフォントとその状態 (フォールバックの順)
+    pub fonts: Vec<(String, FontStatus)>,
+    pub region: Option<String>,
+}
+
+impl CharExplanation {
+    pub fn supplier(&self) -> Option<&str> {
+        self.fonts
+            .iter()
+            .find(|(_, status)| *status == FontStatus::Supplied)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+impl Display for CharExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", CharRange::new_single(self.c))?;
+        match (self.supplier(), &self.region) {
+            (Some(supplier), Some(region)) => {
+                writeln!(f, "  Supplied by: {}", supplier)?;
+                writeln!(f, "  Region: {}", region)?;
+            }
+            (Some(supplier), None) => writeln!(f, "  Supplied by: {}", supplier)?,
+            (None, _) => writeln!(f, "  Not supplied by any font")?,
+        }
+        for (font_name, status) in &self.fonts {
+            if *status != FontStatus::Supplied {
+                writeln!(f, "  {}: {}", font_name, status)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// 文字がどのフォントから、どのCharacterRegionで提供されるかを調べる
+pub fn explain_char(c: u32, fonts: &[FontAttribution], font: &DynamicFont) -> CharExplanation {
+    let supplied = fonts.iter().any(|font| font.assigned.contains(c));
+    let statuses = fonts
+        .iter()
+        .filter_map(|font| {
+            let status = if font.assigned.contains(c) {
+                FontStatus::Supplied
+            } else if font.candidates.contains(c) {
+                if supplied {
+                    FontStatus::Lost
+                } else {
+                    FontStatus::Dropped
+                }
+            } else if font.cmap.contains(c) {
+                FontStatus::Filtered
+            } else {
+                return None;
+            };
+            Some((font.font_name.clone(), status))
+        })
+        .collect();
+
+    let region = font
+        .character_regions
+        .character_region
+        .iter()
+        .find(|region| region.start as u32 <= c && c <= region.end as u32)
+        .map(|region| format_region(region, &font.font_name));
+
+    CharExplanation {
+        c,
+        fonts: statuses,
+        region,
+    }
+}
+
+fn format_region(region: &CharacterRegion, base_font_name: &str) -> String {
+    let range = CharRange::new(region.start as u32, region.end as u32);
+    let mut details = vec![region
+        .font_name
+        .clone()
+        .unwrap_or_else(|| format!("{} (base font)", base_font_name))];
+    if let Some(size) = region.size {
+        details.push(format!("size {}", size));
+    }
+    if let Some(style) = region.style {
+        details.push(format!("{:?}", style));
+    }
+    format!("{} ({})", range, details.join(", "))
+}
+
+// Unicodeブロック毎に分割 (ブロックに属さない文字は"No Block"にまとめる)
+pub fn group_by_block(chars: &CharRangeList) -> Vec<(&'static str, CharRangeList)> {
+    let mut groups: Vec<(&'static str, CharRangeList)> = Vec::new();
+    for range in chars {
+        let mut start = range.start;
+        loop {
+            let block = char::from_u32(start).and_then(find_unicode_block);
+            let (name, end) = match block {
+                Some(block) => (block.name(), block.end().min(range.end)),
+                None => ("No Block", next_block_start(start, range.end)),
+            };
+            let part = CharRange::new(start, end);
+            match groups.last_mut() {
+                Some((last, chars)) if *last == name => chars.add_range(part),
+                _ => groups.push((name, CharRangeList::from(vec![part]))),
+            }
+            if end >= range.end {
+                break;
+            }
+            start = end + 1;
+        }
+    }
+    groups
+}
+
+// ブロックに属さない文字が続く範囲の終端
+fn next_block_start(start: u32, end: u32) -> u32 {
+    (start..=end)
+        .find(|c| char::from_u32(*c).and_then(find_unicode_block).is_some())
+        .map_or(end, |c| c - 1)
+}
+
+// ブロック毎・フォント毎の文字数の表
+pub struct BlockSummary {
+    pub font_names: Vec<String>,
+    // ブロック名とフォント毎の文字数
+    pub rows: Vec<(&'static str, Vec<usize>)>,
+}
+
+impl BlockSummary {
+    pub fn new(fonts: &[FontAttribution]) -> Self {
+        let mut rows: Vec<(&'static str, Vec<usize>)> = Vec::new();
+        let all_chars = fonts
+            .iter()
+            .fold(CharRangeList::new(), |all, font| all.union(&font.assigned));
+        for (block, chars) in group_by_block(&all_chars) {
+            let counts = fonts
+                .iter()
+                .map(|font| font.assigned.intersection(&chars).count())
+                .collect();
+            rows.push((block, counts));
+        }
+        Self {
+            font_names: fonts.iter().map(|font| font.font_name.clone()).collect(),
+            rows,
+        }
+    }
+}
+
+impl Display for BlockSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let block_width = self
+            .rows
+            .iter()
+            .map(|(block, _)| block.len())
+            .chain(["Block".len()])
+            .max()
+            .unwrap_or_default();
+        let widths = self
+            .font_names
+            .iter()
+            .map(|name| name.chars().count().max(5))
+            .collect::<Vec<_>>();
+
+        write!(f, "{:<block_width$}  {:>5}", "Block", "Total")?;
+        for (name, width) in self.font_names.iter().zip(&widths) {
+            write!(f, "  {:>width$}", name)?;
+        }
+        writeln!(f)?;
+        for (block, counts) in &self.rows {
+            write!(
+                f,
+                "{:<block_width$}  {:>5}",
+                block,
+                counts.iter().sum::<usize>()
+            )?;
+            for (count, width) in counts.iter().zip(&widths) {
+                write!(f, "  {:>width$}", count)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xml::{FontStyle, VerticalOffset};
+
+    fn attribution(name: &str, cmap: CharRange, assigned: CharRangeList) -> FontAttribution {
+        let cmap = CharRangeList::from(vec![cmap]);
+        FontAttribution {
+            font_name: name.to_string(),
+            candidates: cmap.clone(),
+            cmap,
+            assigned,
+        }
+    }
+
+    #[test]
+    fn test_explain_char() {
+        let mut fonts = vec![
+            attribution(
+                "Latin",
+                CharRange::new(0x20, 0x3042),
+                CharRangeList::from(vec![CharRange::new(0x20, 0x3042)]),
+            ),
+            attribution(
+                "Japanese",
+                CharRange::new(0x20, 0x9FFF),
+                CharRangeList::from(vec![CharRange::new(0x3043, 0x9FFF)]),
+            ),
+        ];
+        // Japaneseはひらがなのみに絞り込まれている
+        fonts[1].candidates = CharRangeList::from(vec![CharRange::new(0x3040, 0x9FFF)]);
+
+        let mut region = CharacterRegion::new('\u{3043}', '\u{9FFF}');
+        region.font_name = Some("Japanese".to_string());
+        let font = DynamicFont {
+            file_name: "Test".to_string(),
+            font_name: "Latin".to_string(),
+            size: 16.0,
+            spacing: 0.0,
+            use_kerning: true,
+            style: FontStyle::Regular,
+            default_character: '*',
+            vertical_offset: VerticalOffset::DefaultFontAscent,
+            character_regions: vec![CharacterRegion::new(' ', 'あ'), region].into(),
+            bitmap_overrides: Vec::new(),
+        };
+
+        let explanation = explain_char(0x3042, &fonts, &font);
+        assert_eq!(explanation.supplier(), Some("Latin"));
+        assert_eq!(explanation.fonts[1].1, FontStatus::Lost);
+        assert!(explanation.region.unwrap().contains("Latin (base font)"));
+
+        let explanation = explain_char('A' as u32, &fonts, &font);
+        assert_eq!(explanation.fonts[1].1, FontStatus::Filtered);
+
+        let explanation = explain_char(0x56FD, &fonts, &font);
+        assert_eq!(explanation.supplier(), Some("Japanese"));
+        assert!(explanation.region.unwrap().starts_with("\u{3043}(U+3043)"));
+
+        assert!(explain_char(0xAC00, &fonts, &font).fonts.is_empty());
+    }
+
+    #[test]
+    fn test_group_by_block() {
+        let chars = CharRangeList::from(vec![
+            CharRange::new(0x7E, 0xA0),
+            CharRange::new(0x3041, 0x30A1),
+        ]);
+        let groups = group_by_block(&chars);
+        let names = groups.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["Basic Latin", "Latin-1 Supplement", "Hiragana", "Katakana"]
+        );
+        assert_eq!(groups[0].1.count(), 2);
+        assert_eq!(groups[3].1.count(), 2);
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt::Display;
+
+use unicode_blocks::UnicodeBlock;
+use unicode_general_category::{get_general_category, GeneralCategory};
+use unicode_script::{Script, UnicodeScript};
+
+use crate::{
+    charset::Preset,
+    types::{CharRange, CharRangeList},
+};
+
+// フォントが提供する文字を絞り込むためのフィルター
+#[derive(Debug, Clone, PartialEq)]
+pub enum CharFilter {
+    Block(UnicodeBlock),
+    Script(Script),
+    Category(GeneralCategory),
+    Range(CharRange),
+    Preset(Preset),
+    Chars(CharRangeList),
+}
+
+impl CharFilter {
+    // Presetは文字一覧を読み込む必要があるため対象外
+    fn matches(&self, c: u32) -> bool {
+        match self {
+            CharFilter::Block(block) => block.start() <= c && c <= block.end(),
+            CharFilter::Range(range) => range.contains(c),
+            CharFilter::Chars(chars) => chars.contains(c),
+            CharFilter::Script(script) => char::from_u32(c).is_some_and(|c| c.script() == *script),
+            CharFilter::Category(category) => {
+                char::from_u32(c).is_some_and(|c| get_general_category(c) == *category)
+            }
+            CharFilter::Preset(_) => false,
+        }
+    }
+
+    // chars のうちフィルターに一致する文字を取得
+    pub fn select(&self, chars: &CharRangeList) -> anyhow::Result<CharRangeList> {
+        let selected = match self {
+            CharFilter::Block(block) => {
+                select_range(chars, CharRange::new(block.start(), block.end()))
+            }
+            CharFilter::Range(range) => select_range(chars, *range),
+            CharFilter::Script(_) | CharFilter::Category(_) => {
+                // 文字毎にプロパティを確認し、連続する文字を範囲にまとめる
+                let mut selected = Vec::new();
+                for range in chars.ranges() {
+                    let mut run: Option<CharRange> = None;
+                    for c in range.start..=range.end {
+                        if self.matches(c) {
+                            match run.as_mut() {
+                                Some(run) => run.end = c,
+                                None => run = Some(CharRange::new_single(c)),
+                            }
+                        } else if let Some(run) = run.take() {
+                            selected.push(run);
+                        }
+                    }
+                    selected.extend(run);
+                }
+                CharRangeList::from(selected)
+            }
+            CharFilter::Preset(preset) => chars.intersection(&preset.chars()),
+            CharFilter::Chars(list) => chars.intersection(list),
+        };
+        Ok(selected)
+    }
+}
+
+fn select_range(chars: &CharRangeList, range: CharRange) -> CharRangeList {
+    chars.intersection(&CharRangeList::from(vec![range]))
+}
+
+impl From<UnicodeBlock> for CharFilter {
+    fn from(block: UnicodeBlock) -> Self {
+        CharFilter::Block(block)
+    }
+}
+
+impl From<Script> for CharFilter {
+    fn from(script: Script) -> Self {
+        CharFilter::Script(script)
+    }
+}
+
+impl From<GeneralCategory> for CharFilter {
+    fn from(category: GeneralCategory) -> Self {
+        CharFilter::Category(category)
+    }
+}
+
+impl From<Preset> for CharFilter {
+    fn from(preset: Preset) -> Self {
+        CharFilter::Preset(preset)
+    }
+}
+
+impl From<CharRange> for CharFilter {
+    fn from(range: CharRange) -> Self {
+        CharFilter::Range(range)
+    }
+}
+
+impl From<CharRangeList> for CharFilter {
+    fn from(chars: CharRangeList) -> Self {
+        CharFilter::Chars(chars)
+    }
+}
+
+impl Display for CharFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CharFilter::Block(block) => write!(f, "Block({})", block.name()),
+            CharFilter::Script(script) => write!(f, "Script({})", script.full_name()),
+            CharFilter::Category(category) => write!(f, "Category({})", category.abbreviation()),
+            CharFilter::Range(range) => {
+                write!(f, "Range(U+{:04X}-U+{:04X})", range.start, range.end)
+            }
+            CharFilter::Preset(preset) => write!(f, "Preset({})", preset),
+            CharFilter::Chars(chars) => write!(f, "Chars({})", chars),
+        }
+    }
+}
+
+// フォント毎のinclude/exclude/preferフィルター
+#[derive(Debug, Clone, Default)]
+pub struct FontFilter {
+    pub include: Vec<CharFilter>,
+    pub exclude: Vec<CharFilter>,
+    pub prefer: Vec<CharFilter>,
+}
+
+impl FontFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    // includeが指定されていればいずれかに一致する文字のみ残し、excludeに一致する文字を除外
+    pub fn apply(&self, chars: &CharRangeList) -> anyhow::Result<CharRangeList> {
+        let mut result = if self.include.is_empty() {
+            chars.clone()
+        } else {
+            let mut included = CharRangeList::new();
+            for filter in &self.include {
+                included = included.union(&filter.select(chars)?);
+            }
+            included
+        };
+
+        for filter in &self.exclude {
+            result.subtract_range_list(&filter.select(&result)?);
+        }
+
+        Ok(result)
+    }
+
+    // preferのいずれかに一致する文字を取得
+    pub fn preferred(&self, chars: &CharRangeList) -> anyhow::Result<CharRangeList> {
+        let mut preferred = CharRangeList::new();
+        for filter in &self.prefer {
+            preferred = preferred.union(&filter.select(chars)?);
+        }
+        Ok(preferred)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars() -> CharRangeList {
+        CharRangeList::from(vec![
+            CharRange::new(0x0020, 0x007E),
+            CharRange::new(0x3040, 0x30FF),
+            CharRange::new(0x4E00, 0x4E10),
+        ])
+    }
+
+    #[test]
+    fn test_select_block() {
+        let selected = CharFilter::from(unicode_blocks::HIRAGANA)
+            .select(&chars())
+            .unwrap();
+        assert_eq!(selected.ranges(), &[CharRange::new(0x3040, 0x309F)]);
+    }
+
+    #[test]
+    fn test_select_script() {
+        let selected = CharFilter::from(Script::Han).select(&chars()).unwrap();
+        assert_eq!(selected.ranges(), &[CharRange::new(0x4E00, 0x4E10)]);
+    }
+
+    #[test]
+    fn test_select_category() {
+        let selected = CharFilter::from(GeneralCategory::DecimalNumber)
+            .select(&chars())
+            .unwrap();
+        assert_eq!(selected.ranges(), &[CharRange::new(0x0030, 0x0039)]);
+    }
+
+    #[test]
+    fn test_select_preset() {
+        let selected = CharFilter::from(Preset::Ascii).select(&chars()).unwrap();
+        assert_eq!(selected.ranges(), &[CharRange::new(0x0020, 0x007E)]);
+    }
+
+    #[test]
+    fn test_apply_include_and_exclude() {
+        let filter = FontFilter {
+            include: vec![
+                CharFilter::from(unicode_blocks::BASIC_LATIN),
+                CharFilter::from(unicode_blocks::KATAKANA),
+            ],
+            exclude: vec![CharFilter::from(GeneralCategory::UppercaseLetter)],
+            prefer: Vec::new(),
+        };
+        let applied = filter.apply(&chars()).unwrap();
+        assert_eq!(
+            applied.ranges(),
+            &[
+                CharRange::new(0x0020, 0x0040),
+                CharRange::new(0x005B, 0x007E),
+                CharRange::new(0x30A0, 0x30FF),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_empty_filter() {
+        let applied = FontFilter::default().apply(&chars()).unwrap();
+        assert_eq!(applied.ranges(), chars().ranges());
+    }
+}
@@ -0,0 +1,254 @@
+use font_kit::{font::Font, source::Source};
+
+use crate::types::FontNameBundle;
+
+const TAG_OS2: u32 = u32::from_be_bytes(*b"OS/2");
+const TAG_NAME: u32 = u32::from_be_bytes(*b"name");
+const TAG_HHEA: u32 = u32::from_be_bytes(*b"hhea");
+
+// nameテーブルのID
+pub const NAME_ID_COPYRIGHT: u16 = 0;
+pub const NAME_ID_LICENSE: u16 = 13;
+pub const NAME_ID_LICENSE_URL: u16 = 14;
+
+// Windows / English (United States)
+const WINDOWS_PLATFORM_ID: u16 = 3;
+const ENGLISH_LANGUAGE_ID: u16 = 0x409;
+
+// フォントファイルから直接読み出した情報
+#[derive(Debug, Clone, Default)]
+pub struct FontInfo {
+    pub os2: Option<Os2Table>,
+    pub hhea: Option<HheaTable>,
+    pub names: Vec<NameRecord>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Os2Table {
+    pub version: u16,
+    pub fs_type: u16,
+    pub vendor_id: [u8; 4],
+    pub typo_ascender: i16,
+    pub typo_descender: i16,
+    pub typo_line_gap: i16,
+    pub win_ascent: u16,
+    pub win_descent: u16,
+    // version 2以降
+    pub x_height: Option<i16>,
+    pub cap_height: Option<i16>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HheaTable {
+    pub ascender: i16,
+    pub descender: i16,
+    pub line_gap: i16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameRecord {
+    pub platform_id: u16,
+    pub language_id: u16,
+    pub name_id: u16,
+    pub value: String,
+}
+
+impl FontInfo {
+    pub fn load(font_name: &FontNameBundle, source: &dyn Source) -> anyhow::Result<Self> {
+        let font = font_name.get_font_handle(source)?.load()?;
+        Ok(Self::from_font(&font))
+    }
+
+    pub fn from_font(font: &Font) -> Self {
+        Self {
+            os2: font
+                .load_font_table(TAG_OS2)
+                .and_then(|data| Os2Table::parse(&data)),
+            hhea: font
+                .load_font_table(TAG_HHEA)
+                .and_then(|data| HheaTable::parse(&data)),
+            names: font
+                .load_font_table(TAG_NAME)
+                .map(|data| parse_name_table(&data))
+                .unwrap_or_default(),
+        }
+    }
+
+    // 英語の名前を優先して取得
+    pub fn name(&self, name_id: u16) -> Option<&str> {
+        let mut candidates = self.names.iter().filter(|r| r.name_id == name_id);
+        let first = candidates.clone().next();
+        candidates
+            .find(|r| r.platform_id == WINDOWS_PLATFORM_ID && r.language_id == ENGLISH_LANGUAGE_ID)
+            .or(first)
+            .map(|r| r.value.as_str())
+    }
+}
+
+impl Os2Table {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let version = read_u16(data, 0)?;
+        let (x_height, cap_height) = if version >= 2 {
+            (read_i16(data, 86), read_i16(data, 88))
+        } else {
+            (None, None)
+        };
+        Some(Self {
+            version,
+            fs_type: read_u16(data, 8)?,
+            vendor_id: data.get(58..62)?.try_into().ok()?,
+            typo_ascender: read_i16(data, 68)?,
+            typo_descender: read_i16(data, 70)?,
+            typo_line_gap: read_i16(data, 72)?,
+            win_ascent: read_u16(data, 74)?,
+            win_descent: read_u16(data, 76)?,
+            x_height,
+            cap_height,
+        })
+    }
+
+    pub fn vendor_id(&self) -> String {
+        String::from_utf8_lossy(&self.vendor_id)
+            .trim_end_matches(['\0', ' '])
+            .to_string()
+    }
+}
+
+impl HheaTable {
+    fn parse(data: &[u8]) -> Option<Self> {
+        Some(Self {
+            ascender: read_i16(data, 4)?,
+            descender: read_i16(data, 6)?,
+            line_gap: read_i16(data, 8)?,
+        })
+    }
+}
+
+pub fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+pub fn read_i16(data: &[u8], offset: usize) -> Option<i16> {
+    read_u16(data, offset).map(|value| value as i16)
+}
+
+fn parse_name_table(data: &[u8]) -> Vec<NameRecord> {
+    let mut records = Vec::new();
+    let (Some(count), Some(string_offset)) = (read_u16(data, 2), read_u16(data, 4)) else {
+        return records;
+    };
+
+    for i in 0..count as usize {
+        let base = 6 + i * 12;
+        let fields: Option<[u16; 6]> = (0..6)
+            .map(|j| read_u16(data, base + j * 2))
+            .collect::<Option<Vec<_>>>()
+            .and_then(|v| v.try_into().ok());
+        let Some([platform_id, _encoding_id, language_id, name_id, length, offset]) = fields else {
+            break;
+        };
+
+        let start = string_offset as usize + offset as usize;
+        let Some(bytes) = data.get(start..start + length as usize) else {
+            continue;
+        };
+
+        let value = match platform_id {
+            // Unicode / Windows: UTF-16BE
+            0 | 3 => {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect();
+                String::from_utf16_lossy(&units)
+            }
+            // Macintosh: ASCII以外は近似
+            _ => bytes.iter().map(|&b| b as char).collect(),
+        };
+
+        records.push(NameRecord {
+            platform_id,
+            language_id,
+            name_id,
+            value,
+        });
+    }
+
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name_table(records: &[(u16, u16, u16, &str)]) -> Vec<u8> {
+        let mut header = Vec::new();
+        let mut strings = Vec::new();
+        header.extend(0u16.to_be_bytes());
+        header.extend((records.len() as u16).to_be_bytes());
+        header.extend((6 + records.len() as u16 * 12).to_be_bytes());
+        for (platform_id, language_id, name_id, value) in records {
+            let bytes: Vec<u8> = value.encode_utf16().flat_map(|u| u.to_be_bytes()).collect();
+            for field in [
+                *platform_id,
+                1,
+                *language_id,
+                *name_id,
+                bytes.len() as u16,
+                strings.len() as u16,
+            ] {
+                header.extend(field.to_be_bytes());
+            }
+            strings.extend(bytes);
+        }
+        header.extend(strings);
+        header
+    }
+
+    #[test]
+    fn test_parse_name_table() {
+        let data = name_table(&[
+            (3, 0x411, NAME_ID_LICENSE, "ライセンス"),
+            (3, 0x409, NAME_ID_LICENSE, "SIL Open Font License"),
+        ]);
+        let info = FontInfo {
+            os2: None,
+            hhea: None,
+            names: parse_name_table(&data),
+        };
+
+        assert_eq!(info.names.len(), 2);
+        assert_eq!(info.name(NAME_ID_LICENSE), Some("SIL Open Font License"));
+        assert_eq!(info.name(NAME_ID_LICENSE_URL), None);
+    }
+
+    #[test]
+    fn test_parse_os2_table() {
+        let mut data = vec![0u8; 78];
+        data[0..2].copy_from_slice(&4u16.to_be_bytes());
+        data[8..10].copy_from_slice(&0x0002u16.to_be_bytes());
+        data[58..62].copy_from_slice(b"GOOG");
+        data[68..70].copy_from_slice(&880i16.to_be_bytes());
+        data[70..72].copy_from_slice(&(-120i16).to_be_bytes());
+        data[74..76].copy_from_slice(&1160u16.to_be_bytes());
+
+        let os2 = Os2Table::parse(&data).unwrap();
+        assert_eq!(os2.version, 4);
+        assert_eq!(os2.fs_type, 0x0002);
+        assert_eq!(os2.vendor_id(), "GOOG");
+        assert_eq!(os2.typo_ascender, 880);
+        assert_eq!(os2.typo_descender, -120);
+        assert_eq!(os2.win_ascent, 1160);
+        // version 2以降のフィールドが無い
+        assert_eq!(os2.x_height, None);
+        assert!(Os2Table::parse(&data[..10]).is_none());
+
+        data.resize(96, 0);
+        data[86..88].copy_from_slice(&1062i16.to_be_bytes());
+        data[88..90].copy_from_slice(&1466i16.to_be_bytes());
+        let os2 = Os2Table::parse(&data).unwrap();
+        assert_eq!(os2.x_height, Some(1062));
+        assert_eq!(os2.cap_height, Some(1466));
+    }
+}
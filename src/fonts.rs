@@ -6,11 +6,11 @@ use crate::{
 };
 
 pub fn create_font_bundles(base_dir: &Path) -> anyhow::Result<Vec<DynamicFontBuilderBundle>> {
-    let mut bundles = Vec::new();
-    bundles.push(terraria_fonts(base_dir)?);
-    bundles.push(noxusboss_fonts(base_dir)?);
-    bundles.push(terratcg_fonts(base_dir)?);
-    Ok(bundles)
+    Ok(vec![
+        terraria_fonts(base_dir)?,
+        noxusboss_fonts(base_dir)?,
+        terratcg_fonts(base_dir)?,
+    ])
 }
 
 #[allow(unused)]
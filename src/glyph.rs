@@ -0,0 +1,145 @@
+use std::fmt::Display;
+
+use font_kit::font::Font;
+use serde::Serialize;
+use unicode_general_category::{get_general_category, GeneralCategory};
+
+use crate::types::{CharRange, CharRangeList};
+
+// cmapに含まれていても使用しない文字の理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ExcludeReason {
+    // .notdef (グリフID 0) に割り当てられている
+    Notdef,
+    // 空白文字・書式文字以外で輪郭が無い
+    EmptyGlyph,
+    // サロゲート (U+D800-U+DFFF)
+    Surrogate,
+    // U+FFFFより大きい (XNAのcharはUTF-16の1単位のため扱えない)
+    OutsideBmp,
+}
+
+impl Display for ExcludeReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExcludeReason::Notdef => write!(f, "mapped to .notdef"),
+            ExcludeReason::EmptyGlyph => write!(f, "empty glyph"),
+            ExcludeReason::Surrogate => write!(f, "surrogate code point"),
+            ExcludeReason::OutsideBmp => write!(f, "outside the BMP"),
+        }
+    }
+}
+
+// 空白文字とゼロ幅スペース等の書式文字は輪郭が無くても正しいため除外しない
+fn is_intentionally_empty(c: u32) -> bool {
+    char::from_u32(c)
+        .is_some_and(|c| c.is_whitespace() || get_general_category(c) == GeneralCategory::Format)
+}
+
+// cmapの文字のうち、.notdefまたは空のグリフに割り当てられている文字を取得
+pub fn find_invalid_glyphs(
+    font: &Font,
+    chars: &CharRangeList,
+) -> Vec<(ExcludeReason, CharRangeList)> {
+    let mut notdef = CharRangeList::new();
+    let mut empty = CharRangeList::new();
+
+    for c in chars.code_points() {
+        let glyph = char::from_u32(c).and_then(|c| font.glyph_for_char(c));
+        match glyph {
+            None | Some(0) => notdef.add_range(CharRange::new_single(c)),
+            Some(_) if is_intentionally_empty(c) => {}
+            Some(glyph) => {
+                let is_empty = font
+                    .typographic_bounds(glyph)
+                    .map(|bounds| bounds.width() <= 0.0 || bounds.height() <= 0.0)
+                    .unwrap_or(true);
+                if is_empty {
+                    empty.add_range(CharRange::new_single(c));
+                }
+            }
+        }
+    }
+
+    [
+        (ExcludeReason::Notdef, notdef),
+        (ExcludeReason::EmptyGlyph, empty),
+    ]
+    .into_iter()
+    .filter(|(_, chars)| !chars.is_empty())
+    .collect()
+}
+
+// U+FFFFより大きい文字とサロゲートの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(unused)]
+pub enum CodePointPolicy {
+    // 除外してレポートに記録する
+    Drop,
+    // ビルドを失敗させる
+    Error,
+}
+
+// XNAのcharで表せない文字を取得
+pub fn find_unsupported_code_points(chars: &CharRangeList) -> Vec<(ExcludeReason, CharRangeList)> {
+    [
+        (ExcludeReason::Surrogate, CharRange::new(0xD800, 0xDFFF)),
+        (ExcludeReason::OutsideBmp, CharRange::new(0x10000, u32::MAX)),
+    ]
+    .into_iter()
+    .map(|(reason, range)| {
+        (
+            reason,
+            chars.intersection(&CharRangeList::from(vec![range])),
+        )
+    })
+    .filter(|(_, chars)| !chars.is_empty())
+    .collect()
+}
+
+// レポートに記録する除外した文字
+#[derive(Debug, Clone, Serialize)]
+pub struct ExcludedGlyphs {
+    #[serde(rename = "@FontName")]
+    pub font_name: String,
+    #[serde(rename = "@Reason")]
+    pub reason: ExcludeReason,
+    #[serde(rename = "Range")]
+    pub ranges: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_unsupported_code_points() {
+        let chars = CharRangeList::from(vec![
+            CharRange::new(0x20, 0x7E),
+            CharRange::new(0xD7F0, 0xD810),
+            CharRange::new(0xFFF0, 0x1F64F),
+        ]);
+        let unsupported = find_unsupported_code_points(&chars);
+        assert_eq!(unsupported.len(), 2);
+        assert_eq!(unsupported[0].0, ExcludeReason::Surrogate);
+        assert_eq!(unsupported[0].1.ranges(), &[CharRange::new(0xD800, 0xD810)]);
+        assert_eq!(unsupported[1].0, ExcludeReason::OutsideBmp);
+        assert_eq!(
+            unsupported[1].1.ranges(),
+            &[CharRange::new(0x10000, 0x1F64F)]
+        );
+
+        let bmp = CharRangeList::from(vec![CharRange::new(0x20, 0x7E)]);
+        assert!(find_unsupported_code_points(&bmp).is_empty());
+    }
+
+    #[test]
+    fn test_is_intentionally_empty() {
+        assert!(is_intentionally_empty(' ' as u32));
+        assert!(is_intentionally_empty(0x3000));
+        assert!(is_intentionally_empty(0x00A0));
+        assert!(is_intentionally_empty(0x200B));
+        assert!(!is_intentionally_empty('a' as u32));
+        assert!(!is_intentionally_empty(0x3042));
+    }
+}
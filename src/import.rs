@@ -0,0 +1,300 @@
+use std::fmt::Write as _;
+
+use font_kit::source::Source;
+
+use crate::{
+    types::{CharRange, CharRangeList, Counter, FontName},
+    xml::{DynamicFont, FontStyle},
+};
+
+// CharacterRegionをフォント・サイズ・スタイル毎にまとめたもの
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedFont {
+    pub font_name: String,
+    pub size: Option<f32>,
+    pub style: Option<FontStyle>,
+    pub chars: CharRangeList,
+}
+
+// CharacterRegionをフォント毎の文字集合に戻す (基準のフォントが先頭)
+pub fn group_regions(font: &DynamicFont) -> Vec<ImportedFont> {
+    let mut fonts = vec![ImportedFont {
+        font_name: font.font_name.clone(),
+        size: None,
+        style: None,
+        chars: CharRangeList::new(),
+    }];
+    for region in &font.character_regions.character_region {
+        let font_name = region.font_name.as_deref().unwrap_or(&font.font_name);
+        let range = CharRange::new(region.start as u32, region.end as u32);
+        let existing = fonts.iter_mut().find(|imported| {
+            imported.font_name == font_name
+                && imported.size == region.size
+                && imported.style == region.style
+        });
+        match existing {
+            Some(imported) => imported.chars.add_range(range),
+            None => fonts.push(ImportedFont {
+                font_name: font_name.to_string(),
+                size: region.size,
+                style: region.style,
+                chars: CharRangeList::from(vec![range]),
+            }),
+        }
+    }
+    fonts
+}
+
+// インストールされているフォントからFontNameの種類を判定
+fn resolve_font_name(name: &str, source: &dyn Source) -> Option<FontName<'static>> {
+    [
+        FontName::family(name.to_string()),
+        FontName::full(name.to_string()),
+        FontName::postscript(name.to_string()),
+    ]
+    .into_iter()
+    .find(|font_name| font_name.get_font_handle(source).is_ok())
+}
+
+fn font_name_code(font_name: &FontName) -> String {
+    match font_name {
+        FontName::Family(name) => format!("FontName::family({:?})", name),
+        FontName::Full(name) => format!("FontName::full({:?})", name),
+        FontName::PostScript(name) => format!("FontName::postscript({:?})", name),
+    }
+}
+
+// src/fonts.rsに追加するDynamicFontBuilderのコードを生成
+pub fn to_builder_code(font: &DynamicFont, source: &dyn Source) -> String {
+    write_builder_code(font, &|name| {
+        let font_name = resolve_font_name(name, source)?;
+        let cmap = font_name
+            .to_bundle(source)
+            .and_then(|bundle| bundle.characters(source))
+            .ok();
+        Some((font_name, cmap))
+    })
+}
+
+// フォント名からFontNameとcmapの文字を取得 (見つからなければNone)
+type ResolveFont<'a> = dyn Fn(&str) -> Option<(FontName<'static>, Option<CharRangeList>)> + 'a;
+
+fn write_builder_code(font: &DynamicFont, resolve: &ResolveFont) -> String {
+    let mut code = String::new();
+    writeln!(code, "// Imported from {}.dynamicfont", font.file_name).ok();
+    writeln!(code, "bundle.add_font(").ok();
+    writeln!(code, "    DynamicFontBuilder::new()").ok();
+
+    for imported in group_regions(font) {
+        let resolved = resolve(&imported.font_name);
+        match &resolved {
+            Some((_, cmap)) => {
+                // 範囲にあってもcmapに無い文字はビルド時に含まれない
+                let missing = cmap
+                    .as_ref()
+                    .map(|cmap| imported.chars.difference(cmap))
+                    .unwrap_or_default();
+                if !missing.is_empty() {
+                    writeln!(
+                        code,
+                        "        // {} of {} chars are not in the font: {}",
+                        missing.count(),
+                        imported.chars.count(),
+                        missing
+                    )
+                    .ok();
+                }
+            }
+            None => {
+                writeln!(code, "        // Font not found: {}", imported.font_name).ok();
+            }
+        }
+        let font_name = resolved
+            .map(|(font_name, _)| font_name)
+            .unwrap_or_else(|| FontName::full(imported.font_name.to_string()));
+
+        writeln!(code, "        .add_font(").ok();
+        writeln!(
+            code,
+            "            FallbackFont::new({})",
+            font_name_code(&font_name)
+        )
+        .ok();
+        writeln!(
+            code,
+            "                .include({:?}.parse::<CharRangeList>()?)",
+            imported.chars.to_string()
+        )
+        .ok();
+        if let Some(size) = imported.size {
+            writeln!(code, "                .size({}f32)", size).ok();
+        }
+        if let Some(style) = imported.style {
+            writeln!(code, "                .style(FontStyle::{:?})", style).ok();
+        }
+        writeln!(code, "        )").ok();
+    }
+
+    writeln!(code, "        .file_name({:?})", font.file_name).ok();
+    writeln!(code, "        .size({}f32)", font.size).ok();
+    writeln!(code, "        .spacing({}f32)", font.spacing).ok();
+    writeln!(code, "        .use_kerning({})", font.use_kerning).ok();
+    writeln!(code, "        .style(FontStyle::{:?})", font.style).ok();
+    writeln!(
+        code,
+        "        .default_character({:?})",
+        font.default_character
+    )
+    .ok();
+    writeln!(
+        code,
+        "        .vertical_offset(VerticalOffset::{:?}),",
+        font.vertical_offset
+    )
+    .ok();
+    writeln!(code, ");").ok();
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xml::{CharacterRegion, VerticalOffset, XnaContent};
+
+    #[test]
+    fn test_group_regions() {
+        let mut bold = CharacterRegion::new('ア', 'ン');
+        bold.font_name = Some("Japanese".to_string());
+        bold.style = Some(FontStyle::Bold);
+        let mut hiragana = CharacterRegion::new('あ', 'ん');
+        hiragana.font_name = Some("Japanese".to_string());
+        let mut kanji = CharacterRegion::new('一', '龠');
+        kanji.font_name = Some("Japanese".to_string());
+
+        let font = DynamicFont {
+            file_name: "Test".to_string(),
+            font_name: "Latin".to_string(),
+            size: 16.0,
+            spacing: 0.0,
+            use_kerning: true,
+            style: FontStyle::Regular,
+            default_character: '*',
+            vertical_offset: VerticalOffset::DefaultFontAscent,
+            character_regions: vec![hiragana, CharacterRegion::new(' ', '~'), bold, kanji].into(),
+            bitmap_overrides: Vec::new(),
+        };
+
+        let fonts = group_regions(&font);
+        assert_eq!(fonts.len(), 3);
+        assert_eq!(fonts[0].font_name, "Latin");
+        assert_eq!(fonts[0].chars.to_string(), "U+0020-007E");
+        assert_eq!(fonts[1].font_name, "Japanese");
+        assert_eq!(fonts[1].chars.to_string(), "U+3042-3093, U+4E00-9FA0");
+        assert_eq!(fonts[2].style, Some(FontStyle::Bold));
+    }
+
+    #[test]
+    fn test_to_builder_code() {
+        let content = r#"<?xml version="1.0" encoding="utf-8"?>
+<XnaContent xmlns:Graphics="ReLogic.Content.Pipeline">
+  <Asset Type="Graphics:DynamicFontDescription">
+    <FontName>Andy Bold</FontName>
+    <Size>16</Size>
+    <Spacing>1</Spacing>
+    <UseKerning>true</UseKerning>
+    <Style>Regular</Style>
+    <DefaultCharacter>*</DefaultCharacter>
+    <VerticalOffset>MaxAscent</VerticalOffset>
+    <CharacterRegions>
+      <CharacterRegion>
+        <Start>&#32;</Start>
+        <End>~</End>
+      </CharacterRegion>
+      <CharacterRegion>
+        <FontName>Noto Sans JP Bold</FontName>
+        <Size>14.5</Size>
+        <Style>Bold</Style>
+        <Start>&#x3041;</Start>
+        <End>&#x3096;</End>
+      </CharacterRegion>
+      <CharacterRegion>
+        <FontName>Noto Sans JP Bold</FontName>
+        <Size>14.5</Size>
+        <Style>Bold</Style>
+        <Start>"</Start>
+        <End>"</End>
+      </CharacterRegion>
+      <CharacterRegion>
+        <FontName>Missing Font</FontName>
+        <Start>&#x4E00;</Start>
+        <End>&#x4E01;</End>
+      </CharacterRegion>
+    </CharacterRegions>
+  </Asset>
+</XnaContent>"#;
+        let mut font = XnaContent::from_xml(content).unwrap().asset.font;
+        font.file_name = "Andy".to_string();
+
+        // インストールされているフォントの代わり
+        let code = write_builder_code(&font, &|name| match name {
+            "Andy Bold" => Some((FontName::family("Andy Bold"), None)),
+            "Noto Sans JP Bold" => Some((
+                FontName::full("Noto Sans JP Bold"),
+                Some("U+3041-3094, \"".parse().unwrap()),
+            )),
+            _ => None,
+        });
+
+        let expected = r#"// Imported from Andy.dynamicfont
+bundle.add_font(
+    DynamicFontBuilder::new()
+        .add_font(
+            FallbackFont::new(FontName::family("Andy Bold"))
+                .include("U+0020-007E".parse::<CharRangeList>()?)
+        )
+        // 2 of 87 chars are not in the font: U+3095-3096
+        .add_font(
+            FallbackFont::new(FontName::full("Noto Sans JP Bold"))
+                .include("\", U+3041-3096".parse::<CharRangeList>()?)
+                .size(14.5f32)
+                .style(FontStyle::Bold)
+        )
+        // Font not found: Missing Font
+        .add_font(
+            FallbackFont::new(FontName::full("Missing Font"))
+                .include("U+4E00-4E01".parse::<CharRangeList>()?)
+        )
+        .file_name("Andy")
+        .size(16f32)
+        .spacing(1f32)
+        .use_kerning(true)
+        .style(FontStyle::Regular)
+        .default_character('*')
+        .vertical_offset(VerticalOffset::MaxAscent),
+);
+"#;
+        assert_eq!(code, expected);
+
+        // includeの文字列リテラルはCharRangeListに戻せる
+        let included = code
+            .lines()
+            .filter_map(|line| {
+                line.trim()
+                    .strip_prefix(".include(\"")?
+                    .strip_suffix("\".parse::<CharRangeList>()?)")
+            })
+            .map(|literal| {
+                literal
+                    .replace("\\\"", "\"")
+                    .replace("\\\\", "\\")
+                    .parse::<CharRangeList>()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let grouped = group_regions(&font)
+            .into_iter()
+            .map(|imported| imported.chars)
+            .collect::<Vec<_>>();
+        assert_eq!(included, grouped);
+    }
+}
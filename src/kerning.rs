@@ -0,0 +1,323 @@
+use std::collections::{HashMap, HashSet};
+
+use font_kit::font::Font;
+
+use crate::font_info::{read_i16, read_u16};
+
+const TAG_GPOS: u32 = u32::from_be_bytes(*b"GPOS");
+const TAG_KERN: u32 = u32::from_be_bytes(*b"kern");
+const FEATURE_KERN: &[u8; 4] = b"kern";
+
+// GPOSのLookupの種類
+const LOOKUP_PAIR_ADJUSTMENT: u16 = 2;
+const LOOKUP_EXTENSION: u16 = 9;
+
+// ValueRecordのXAdvance
+const VALUE_X_ADVANCE: u16 = 0x0004;
+
+// グリフの組み合わせ毎のカーニング (フォントの単位)
+// GPOSのkern機能を優先し、無い場合は古いkernテーブルを使用する
+pub fn kerning_pairs(font: &Font, glyphs: &HashSet<u16>) -> HashMap<(u16, u16), i16> {
+    let gpos = font
+        .load_font_table(TAG_GPOS)
+        .map(|data| parse_gpos(&data, glyphs))
+        .unwrap_or_default();
+    if !gpos.is_empty() {
+        return gpos;
+    }
+    font.load_font_table(TAG_KERN)
+        .map(|data| parse_kern(&data, glyphs))
+        .unwrap_or_default()
+}
+
+// kernテーブルのformat 0 (水平方向のみ)
+fn parse_kern(data: &[u8], glyphs: &HashSet<u16>) -> HashMap<(u16, u16), i16> {
+    let mut pairs = HashMap::new();
+    let Some(table_count) = read_u16(data, 2) else {
+        return pairs;
+    };
+
+    let mut offset = 4;
+    for _ in 0..table_count {
+        let (Some(length), Some(coverage)) =
+            (read_u16(data, offset + 2), read_u16(data, offset + 4))
+        else {
+            break;
+        };
+        // format 0、水平方向、最小値・交差方向でない
+        if coverage >> 8 == 0 && coverage & 0x07 == 0x01 {
+            let pair_count = read_u16(data, offset + 6).unwrap_or(0) as usize;
+            for i in 0..pair_count {
+                let record = offset + 14 + i * 6;
+                let (Some(left), Some(right), Some(value)) = (
+                    read_u16(data, record),
+                    read_u16(data, record + 2),
+                    read_i16(data, record + 4),
+                ) else {
+                    break;
+                };
+                if value != 0 && glyphs.contains(&left) && glyphs.contains(&right) {
+                    pairs.entry((left, right)).or_insert(value);
+                }
+            }
+        }
+        offset += length as usize;
+    }
+    pairs
+}
+
+// GPOSのkern機能のPair Adjustment (1つ目のグリフのXAdvanceのみ)
+fn parse_gpos(data: &[u8], glyphs: &HashSet<u16>) -> HashMap<(u16, u16), i16> {
+    let mut pairs = HashMap::new();
+    let (Some(feature_list), Some(lookup_list)) = (read_u16(data, 6), read_u16(data, 8)) else {
+        return pairs;
+    };
+    let (feature_list, lookup_list) = (feature_list as usize, lookup_list as usize);
+
+    // kern機能が参照するLookup (スクリプト・言語は区別しない)
+    let mut lookup_indices = Vec::new();
+    let feature_count = read_u16(data, feature_list).unwrap_or(0) as usize;
+    for i in 0..feature_count {
+        let record = feature_list + 2 + i * 6;
+        if data.get(record..record + 4) != Some(FEATURE_KERN) {
+            continue;
+        }
+        let Some(feature) = read_u16(data, record + 4) else {
+            continue;
+        };
+        let feature = feature_list + feature as usize;
+        let index_count = read_u16(data, feature + 2).unwrap_or(0) as usize;
+        lookup_indices.extend((0..index_count).filter_map(|j| read_u16(data, feature + 4 + j * 2)));
+    }
+    lookup_indices.sort();
+    lookup_indices.dedup();
+
+    for lookup_index in lookup_indices {
+        let Some(lookup) = read_u16(data, lookup_list + 2 + lookup_index as usize * 2) else {
+            continue;
+        };
+        let lookup = lookup_list + lookup as usize;
+        let lookup_type = read_u16(data, lookup).unwrap_or(0);
+        let subtable_count = read_u16(data, lookup + 4).unwrap_or(0) as usize;
+        for i in 0..subtable_count {
+            let Some(subtable) = read_u16(data, lookup + 6 + i * 2) else {
+                break;
+            };
+            let mut subtable = lookup + subtable as usize;
+            let mut subtable_type = lookup_type;
+            if lookup_type == LOOKUP_EXTENSION {
+                subtable_type = read_u16(data, subtable + 2).unwrap_or(0);
+                subtable += read_u32(data, subtable + 4).unwrap_or(0) as usize;
+            }
+            if subtable_type == LOOKUP_PAIR_ADJUSTMENT {
+                parse_pair_pos(data, subtable, glyphs, &mut pairs);
+            }
+        }
+    }
+    pairs
+}
+
+// 先に見つかった値を優先する
+fn parse_pair_pos(
+    data: &[u8],
+    offset: usize,
+    glyphs: &HashSet<u16>,
+    pairs: &mut HashMap<(u16, u16), i16>,
+) {
+    let (Some(format), Some(coverage), Some(value_format1), Some(value_format2)) = (
+        read_u16(data, offset),
+        read_u16(data, offset + 2),
+        read_u16(data, offset + 4),
+        read_u16(data, offset + 6),
+    ) else {
+        return;
+    };
+    if value_format1 & VALUE_X_ADVANCE == 0 {
+        return;
+    }
+    let coverage = read_coverage(data, offset + coverage as usize);
+    let x_advance = 2 * (value_format1 & (VALUE_X_ADVANCE - 1)).count_ones() as usize;
+    let record_size = 2 * (value_format1.count_ones() + value_format2.count_ones()) as usize;
+
+    match format {
+        // グリフの組み合わせ毎
+        1 => {
+            for (coverage_index, first) in coverage.iter().enumerate() {
+                if !glyphs.contains(first) {
+                    continue;
+                }
+                let Some(pair_set) = read_u16(data, offset + 10 + coverage_index * 2) else {
+                    break;
+                };
+                let pair_set = offset + pair_set as usize;
+                let pair_count = read_u16(data, pair_set).unwrap_or(0) as usize;
+                for i in 0..pair_count {
+                    let record = pair_set + 2 + i * (2 + record_size);
+                    let (Some(second), Some(value)) = (
+                        read_u16(data, record),
+                        read_i16(data, record + 2 + x_advance),
+                    ) else {
+                        break;
+                    };
+                    if value != 0 && glyphs.contains(&second) {
+                        pairs.entry((*first, second)).or_insert(value);
+                    }
+                }
+            }
+        }
+        // クラスの組み合わせ毎
+        2 => {
+            let (Some(class_def1), Some(class_def2), Some(class1_count), Some(class2_count)) = (
+                read_u16(data, offset + 8),
+                read_u16(data, offset + 10),
+                read_u16(data, offset + 12),
+                read_u16(data, offset + 14),
+            ) else {
+                return;
+            };
+            let class_def1 = read_class_def(data, offset + class_def1 as usize);
+            let class_def2 = read_class_def(data, offset + class_def2 as usize);
+
+            // 使用するグリフをクラス毎にまとめる (ClassDefに無いグリフはクラス0)
+            let mut firsts: HashMap<u16, Vec<u16>> = HashMap::new();
+            for first in coverage.iter().filter(|glyph| glyphs.contains(glyph)) {
+                let class = class_def1.get(first).copied().unwrap_or(0);
+                firsts.entry(class).or_default().push(*first);
+            }
+            let mut seconds: HashMap<u16, Vec<u16>> = HashMap::new();
+            for second in glyphs {
+                let class = class_def2.get(second).copied().unwrap_or(0);
+                seconds.entry(class).or_default().push(*second);
+            }
+
+            for (class1, firsts) in &firsts {
+                if *class1 >= class1_count {
+                    continue;
+                }
+                for (class2, seconds) in &seconds {
+                    if *class2 >= class2_count {
+                        continue;
+                    }
+                    let record = offset
+                        + 16
+                        + (*class1 as usize * class2_count as usize + *class2 as usize)
+                            * record_size;
+                    let Some(value) = read_i16(data, record + x_advance).filter(|v| *v != 0) else {
+                        continue;
+                    };
+                    for first in firsts {
+                        for second in seconds {
+                            pairs.entry((*first, *second)).or_insert(value);
+                        }
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// Coverageのグリフ (Coverage Indexの順)
+fn read_coverage(data: &[u8], offset: usize) -> Vec<u16> {
+    let count = read_u16(data, offset + 2).unwrap_or(0) as usize;
+    match read_u16(data, offset) {
+        Some(1) => (0..count)
+            .map_while(|i| read_u16(data, offset + 4 + i * 2))
+            .collect(),
+        Some(2) => {
+            let mut glyphs = Vec::new();
+            for i in 0..count {
+                let record = offset + 4 + i * 6;
+                let (Some(start), Some(end)) = (read_u16(data, record), read_u16(data, record + 2))
+                else {
+                    break;
+                };
+                glyphs.extend(start..=end);
+            }
+            glyphs
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn read_class_def(data: &[u8], offset: usize) -> HashMap<u16, u16> {
+    let mut classes = HashMap::new();
+    match read_u16(data, offset) {
+        Some(1) => {
+            let start = read_u16(data, offset + 2).unwrap_or(0);
+            let count = read_u16(data, offset + 4).unwrap_or(0);
+            for i in 0..count {
+                let Some(class) = read_u16(data, offset + 6 + i as usize * 2) else {
+                    break;
+                };
+                classes.insert(start.wrapping_add(i), class);
+            }
+        }
+        Some(2) => {
+            let count = read_u16(data, offset + 2).unwrap_or(0) as usize;
+            for i in 0..count {
+                let record = offset + 4 + i * 6;
+                let (Some(start), Some(end), Some(class)) = (
+                    read_u16(data, record),
+                    read_u16(data, record + 2),
+                    read_u16(data, record + 4),
+                ) else {
+                    break;
+                };
+                classes.extend((start..=end).map(|glyph| (glyph, class)));
+            }
+        }
+        _ => {}
+    }
+    classes
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn be(values: &[u16]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_kern() {
+        // version, nTables, subtable (version, length, coverage, nPairs, ...)
+        let mut data = be(&[0, 1, 0, 14 + 12, 0x0001, 2, 12, 1, 0]);
+        data.extend(be(&[10, 11, (-50i16) as u16, 10, 12, 30]));
+        let glyphs = HashSet::from([10, 11]);
+        let pairs = parse_kern(&data, &glyphs);
+        assert_eq!(pairs, HashMap::from([((10, 11), -50)]));
+    }
+
+    #[test]
+    fn test_parse_pair_pos() {
+        let glyphs = HashSet::from([1, 2, 3]);
+
+        // format 1: Coverage [1]、PairSet [(2, XPlacement=5, XAdvance=-40), (4, ...)]
+        let mut data = be(&[1, 12, 0x0005, 0, 1, 18]);
+        data.extend(be(&[1, 1, 1]));
+        data.extend(be(&[2, 2, 5, (-40i16) as u16, 4, 0, 7]));
+        let mut pairs = HashMap::new();
+        parse_pair_pos(&data, 0, &glyphs, &mut pairs);
+        assert_eq!(pairs, HashMap::from([((1, 2), -40)]));
+
+        // format 2: 1つ目のクラス1 = {1, 2}、2つ目のクラス1 = {3}
+        let mut data = be(&[2, 24, 0x0004, 0, 34, 44, 2, 2]);
+        data.extend(be(&[0, 0, 0, (-20i16) as u16]));
+        data.extend(be(&[2, 1, 1, 2, 0]));
+        data.extend(be(&[1, 1, 2, 1, 1]));
+        data.extend(be(&[1, 3, 1, 1]));
+        let mut pairs = HashMap::new();
+        parse_pair_pos(&data, 0, &glyphs, &mut pairs);
+        assert_eq!(pairs, HashMap::from([((1, 3), -20), ((2, 3), -20)]));
+    }
+}
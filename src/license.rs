@@ -0,0 +1,224 @@
+use std::fmt::Display;
+
+use font_kit::source::Source;
+use serde::Serialize;
+
+use crate::{
+    font_info::{FontInfo, NAME_ID_COPYRIGHT, NAME_ID_LICENSE, NAME_ID_LICENSE_URL},
+    types::FontNameBundle,
+};
+
+// OS/2 fsTypeのビット
+const FS_TYPE_RESTRICTED: u16 = 0x0002;
+const FS_TYPE_PREVIEW_AND_PRINT: u16 = 0x0004;
+const FS_TYPE_EDITABLE: u16 = 0x0008;
+const FS_TYPE_NO_SUBSETTING: u16 = 0x0100;
+const FS_TYPE_BITMAP_ONLY: u16 = 0x0200;
+
+// OS/2 fsTypeの埋め込み許可レベル
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum EmbeddingPermission {
+    Installable,
+    Restricted,
+    PreviewAndPrint,
+    Editable,
+}
+
+impl EmbeddingPermission {
+    // 複数のビットが立っている場合は最も制限の緩いものを採用する (OpenType仕様)
+    pub fn from_fs_type(fs_type: u16) -> Self {
+        if fs_type & FS_TYPE_EDITABLE != 0 {
+            EmbeddingPermission::Editable
+        } else if fs_type & FS_TYPE_PREVIEW_AND_PRINT != 0 {
+            EmbeddingPermission::PreviewAndPrint
+        } else if fs_type & FS_TYPE_RESTRICTED != 0 {
+            EmbeddingPermission::Restricted
+        } else {
+            EmbeddingPermission::Installable
+        }
+    }
+}
+
+impl Display for EmbeddingPermission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmbeddingPermission::Installable => write!(f, "installable"),
+            EmbeddingPermission::Restricted => write!(f, "restricted"),
+            EmbeddingPermission::PreviewAndPrint => write!(f, "preview & print"),
+            EmbeddingPermission::Editable => write!(f, "editable"),
+        }
+    }
+}
+
+// nameテーブルから推定したライセンスの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LicenseKind {
+    #[serde(rename = "OFL-1.1")]
+    Ofl,
+    #[serde(rename = "Apache-2.0")]
+    Apache,
+    #[serde(rename = "UFL-1.0")]
+    Ubuntu,
+    #[serde(rename = "IPA")]
+    Ipa,
+    #[serde(rename = "Unknown")]
+    Unknown,
+}
+
+impl LicenseKind {
+    pub fn detect(license: Option<&str>, license_url: Option<&str>) -> Self {
+        let text = format!(
+            "{} {}",
+            license.unwrap_or_default(),
+            license_url.unwrap_or_default()
+        )
+        .to_lowercase();
+
+        if text.contains("open font license")
+            || text.contains("openfontlicense.org")
+            || text.contains("scripts.sil.org/ofl")
+        {
+            LicenseKind::Ofl
+        } else if text.contains("apache license") || text.contains("apache.org/licenses") {
+            LicenseKind::Apache
+        } else if text.contains("ubuntu font licence") {
+            LicenseKind::Ubuntu
+        } else if text.contains("ipa font license") || text.contains("ipa.go.jp") {
+            LicenseKind::Ipa
+        } else {
+            LicenseKind::Unknown
+        }
+    }
+}
+
+impl Display for LicenseKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LicenseKind::Ofl => write!(f, "OFL-1.1"),
+            LicenseKind::Apache => write!(f, "Apache-2.0"),
+            LicenseKind::Ubuntu => write!(f, "UFL-1.0"),
+            LicenseKind::Ipa => write!(f, "IPA"),
+            LicenseKind::Unknown => write!(f, "needs review"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct FontLicense {
+    #[serde(rename = "@FontName")]
+    pub full_name: String,
+    #[serde(rename = "@Kind")]
+    pub kind: LicenseKind,
+    pub vendor_id: String,
+    pub fs_type: String,
+    pub embedding: EmbeddingPermission,
+    pub no_subsetting: bool,
+    pub bitmap_only: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub copyright: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_url: Option<String>,
+}
+
+impl FontLicense {
+    pub fn load(font_name: &FontNameBundle, source: &dyn Source) -> anyhow::Result<Self> {
+        let info = FontInfo::load(font_name, source)?;
+        Ok(Self::from_info(font_name.full.to_string(), &info))
+    }
+
+    pub fn from_info(full_name: String, info: &FontInfo) -> Self {
+        let fs_type = info.os2.map(|os2| os2.fs_type).unwrap_or_default();
+        let license = info.name(NAME_ID_LICENSE).map(str::to_string);
+        let license_url = info.name(NAME_ID_LICENSE_URL).map(str::to_string);
+
+        Self {
+            full_name,
+            kind: LicenseKind::detect(license.as_deref(), license_url.as_deref()),
+            vendor_id: info.os2.map(|os2| os2.vendor_id()).unwrap_or_default(),
+            fs_type: format!("0x{:04X}", fs_type),
+            embedding: EmbeddingPermission::from_fs_type(fs_type),
+            no_subsetting: fs_type & FS_TYPE_NO_SUBSETTING != 0,
+            bitmap_only: fs_type & FS_TYPE_BITMAP_ONLY != 0,
+            copyright: info.name(NAME_ID_COPYRIGHT).map(str::to_string),
+            license,
+            license_url,
+        }
+    }
+
+    pub fn is_restricted(&self) -> bool {
+        self.embedding == EmbeddingPermission::Restricted
+    }
+}
+
+impl Display for FontLicense {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} [{}] vendor: {}, embedding: {} ({})",
+            self.full_name,
+            self.kind,
+            if self.vendor_id.is_empty() {
+                "-"
+            } else {
+                &self.vendor_id
+            },
+            self.embedding,
+            self.fs_type
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_permission() {
+        assert_eq!(
+            EmbeddingPermission::from_fs_type(0x0000),
+            EmbeddingPermission::Installable
+        );
+        assert_eq!(
+            EmbeddingPermission::from_fs_type(0x0002),
+            EmbeddingPermission::Restricted
+        );
+        assert_eq!(
+            EmbeddingPermission::from_fs_type(0x0102),
+            EmbeddingPermission::Restricted
+        );
+        // 複数指定されている場合は緩い方を採用
+        assert_eq!(
+            EmbeddingPermission::from_fs_type(0x0006),
+            EmbeddingPermission::PreviewAndPrint
+        );
+        assert_eq!(
+            EmbeddingPermission::from_fs_type(0x0008),
+            EmbeddingPermission::Editable
+        );
+    }
+
+    #[test]
+    fn test_detect_license_kind() {
+        assert_eq!(
+            LicenseKind::detect(
+                Some(
+                    "This Font Software is licensed under the SIL Open Font License, Version 1.1."
+                ),
+                None
+            ),
+            LicenseKind::Ofl
+        );
+        assert_eq!(
+            LicenseKind::detect(None, Some("https://openfontlicense.org")),
+            LicenseKind::Ofl
+        );
+        assert_eq!(
+            LicenseKind::detect(None, Some("http://www.apache.org/licenses/LICENSE-2.0")),
+            LicenseKind::Apache
+        );
+        assert_eq!(LicenseKind::detect(None, None), LicenseKind::Unknown);
+    }
+}
@@ -1,24 +1,85 @@
 use std::{
     fs::{self, File},
     io::{BufWriter, Write as _},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use anyhow::Context as _;
 
-use crate::fonts::create_font_bundles;
+use crate::{
+    explain::{explain_char, BlockSummary},
+    fonts::create_font_bundles,
+    types::{CharRange, CharRangeList},
+    xml::XnaContent,
+};
 
+mod atlas;
+mod bitmap;
+mod bmfont;
+mod budget;
+mod charset;
+mod corpus;
+mod diff;
+mod explain;
+mod filter;
+mod font_info;
 mod fonts;
+mod glyph;
+mod import;
+mod kerning;
+mod license;
+mod metrics;
+mod notation;
+mod output;
+mod region;
+mod report;
+mod required;
+mod spritefont;
 mod types;
+mod vendor;
 mod wrapper;
 mod xml;
+mod xnb;
 
 #[allow(unused)]
 static MOD_SOURCE: &str = r"C:\Users\eva828\Documents\My Games\Terraria\tModLoader\ModSources\";
 
+const COMMANDS: &str = "build, generate, vendor, explain, diff, import, help";
+
 fn main() -> anyhow::Result<()> {
     // std::env::set_var("RUST_BACKTRACE", "1");
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None | Some("build") => build_all_fonts(),
+        Some("vendor") => vendor_all_fonts(),
+        Some("generate") => generate_dynamic_fonts(
+            &args
+                .get(1)
+                .map(PathBuf::from)
+                .unwrap_or_else(|| Path::new("fonts").join("dynamic")),
+        )
+        .map(|_| ()),
+        Some("diff") => diff_fonts(&args[1..]),
+        Some("import") => import_fonts(&args[1..]),
+        Some("explain") => explain_font(
+            args.get(1).map(String::as_str),
+            args.get(2).map(String::as_str),
+        ),
+        // diff --refで古いバイナリのコマンドを判定するため、一覧の書式は変更しない
+        Some("help") => {
+            println!("Available commands: {}", COMMANDS);
+            Ok(())
+        }
+        Some(command) => anyhow::bail!(
+            "Unknown command: {}\nAvailable commands: {}",
+            command,
+            COMMANDS
+        ),
+    }
+}
+
+fn build_all_fonts() -> anyhow::Result<()> {
     export_all_fonts()?;
 
     let dyn_font_dir = Path::new("fonts").join("dynamic");
@@ -28,24 +89,10 @@ fn main() -> anyhow::Result<()> {
     //     .join("Assets")
     //     .join("Fonts");
 
-    // フォルダをリセット
-    println!("Clearing directories...");
-    fs::remove_dir_all(&dyn_font_dir).ok();
-    fs::create_dir_all(&dyn_font_dir)?;
+    let bundle_directories = generate_dynamic_fonts(&dyn_font_dir)?;
     // fs::remove_dir_all(&xnb_font_dir).ok();
     // fs::create_dir_all(&xnb_font_dir)?;
 
-    // DynamicFontBuilderBundleを作成
-    let bundles =
-        create_font_bundles(&dyn_font_dir).with_context(|| "Failed to create font bundles")?;
-
-    // ビルドしdynamicfontファイルを書き出し
-    println!("Generating .dynamicfont files...\n");
-    let bundle_directories: Vec<_> = bundles.iter().map(|b| b.directory.clone()).collect();
-    for bundle in bundles {
-        bundle.build()?;
-    }
-
     // xnbファイルに変換
     println!("Executing DynamicFontGenerator.exe...");
     for dir in bundle_directories {
@@ -67,6 +114,187 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+// .dynamicfontファイルとreport.xmlのみを書き出す
+fn generate_dynamic_fonts(dyn_font_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    // フォルダをリセット (このツールが書き出したファイルのみの場合)
+    println!("Clearing directories...");
+    clear_output_dir(dyn_font_dir)?;
+    build_dynamic_fonts(dyn_font_dir)
+}
+
+// フォルダを削除せずにビルドする (新しく作成したフォルダ用)
+fn build_dynamic_fonts(dyn_font_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dyn_font_dir)?;
+
+    // DynamicFontBuilderBundleを作成
+    let bundles =
+        create_font_bundles(dyn_font_dir).with_context(|| "Failed to create font bundles")?;
+
+    // ビルドしdynamicfontファイルを書き出し
+    println!("Generating .dynamicfont files...\n");
+    let bundle_directories: Vec<_> = bundles.iter().map(|b| b.directory.clone()).collect();
+    for bundle in bundles {
+        bundle.build()?;
+    }
+
+    Ok(bundle_directories)
+}
+
+// 出力先のフォルダを空にする
+// 他のファイルを消さないように、このツールが書き出すファイル以外がある場合は例外
+fn clear_output_dir(dir: &Path) -> anyhow::Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    let query = dir.join("**/*").to_string_lossy().to_string();
+    for path in glob::glob(&query)? {
+        let path = path?;
+        if path.is_file() && !is_generated_file(&path) {
+            anyhow::bail!(
+                "{} contains files not written by this tool: {}\n\
+                Specify an empty directory or a previous output directory.",
+                dir.display(),
+                path.display()
+            );
+        }
+    }
+    fs::remove_dir_all(dir).with_context(|| format!("Failed to clear {}", dir.display()))
+}
+
+// .dynamicfont、report.xml、FontOutputとDynamicFontGeneratorが書き出すファイル
+fn is_generated_file(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let in_bmfont_dir = path
+        .parent()
+        .and_then(Path::file_name)
+        .is_some_and(|dir| dir == "bmfont");
+    path.file_name().is_some_and(|name| name == "report.xml")
+        || matches!(extension, "dynamicfont" | "spritefont" | "xnb")
+        || (in_bmfont_dir && matches!(extension, "fnt" | "png"))
+}
+
+fn vendor_all_fonts() -> anyhow::Result<()> {
+    let dyn_font_dir = Path::new("fonts").join("dynamic");
+    let vendor_dir = Path::new(vendor::VENDOR_DIR);
+
+    let bundles =
+        create_font_bundles(&dyn_font_dir).with_context(|| "Failed to create font bundles")?;
+
+    // 使用されている全てのフォントをvendorディレクトリにコピー
+    println!("Vendoring fonts...");
+    let vendored = vendor::vendor_fonts(&bundles, vendor_dir)?;
+    for font in vendored {
+        println!("{} => {}", font.full_name, font.path.display());
+    }
+
+    // vendorのコピーではなくシステムフォントから読み込まれるフォント
+    let system_fonts = vendor::system_resolved_fonts(&bundles, vendor_dir)?;
+    if !system_fonts.is_empty() {
+        println!("Warning: These fonts still resolve from the system fonts:");
+        for (full_name, path) in system_fonts {
+            println!("  {} ({})", full_name, path.display());
+        }
+    }
+
+    Ok(())
+}
+
+// 文字がどのフォントから提供されるかを表示
+fn explain_font(name: Option<&str>, query: Option<&str>) -> anyhow::Result<()> {
+    let name = name.context("Usage: explain <bundle or font> [characters or U+XXXX]")?;
+    let dyn_font_dir = Path::new("fonts").join("dynamic");
+
+    // U+で始まる場合は文字集合の表記、それ以外は文字列として扱う
+    let chars = match query {
+        Some(query) if query.starts_with("U+") || query.starts_with("u+") => {
+            query.parse::<CharRangeList>()?
+        }
+        Some(query) => query
+            .chars()
+            .map(|c| CharRange::new_single(c as u32))
+            .collect(),
+        None => CharRangeList::new(),
+    };
+
+    let bundles =
+        create_font_bundles(&dyn_font_dir).with_context(|| "Failed to create font bundles")?;
+
+    // バンドル名が一致する場合はバンドル内の全てのフォント
+    let builders = bundles
+        .into_iter()
+        .flat_map(|bundle| {
+            let is_bundle = bundle.directory.file_name().is_some_and(|dir| dir == name);
+            bundle
+                .fonts
+                .into_iter()
+                .filter(move |font| is_bundle || font.output_name() == name)
+        })
+        .collect::<Vec<_>>();
+    if builders.is_empty() {
+        anyhow::bail!("No bundle or font named {}", name);
+    }
+
+    for builder in builders {
+        let (font, report) = builder.build()?;
+        println!("{}:", font.file_name);
+        for c in chars.code_points() {
+            println!("{}", explain_char(c, &report.attribution, &font));
+        }
+        println!("{}", BlockSummary::new(&report.attribution));
+    }
+
+    Ok(())
+}
+
+// 2つのビルド結果、または現在の設定とgit refの時点の設定を比較
+fn diff_fonts(args: &[String]) -> anyhow::Result<()> {
+    let usage = "Usage: diff <old> <new> | diff --ref <git ref>";
+    let (old, new) = match args {
+        [flag, git_ref] if flag == "--ref" => {
+            let temp_dir = std::env::temp_dir().join(format!("font-diff-{}", std::process::id()));
+            let (old_dir, new_dir) = (temp_dir.join("old"), temp_dir.join("new"));
+
+            println!("Building fonts at {}...", git_ref);
+            let result = diff::generate_at_ref(git_ref, &old_dir)
+                .and_then(|_| {
+                    println!("Building current fonts...");
+                    build_dynamic_fonts(&new_dir)
+                })
+                .and_then(|_| Ok((diff::load(&old_dir)?, diff::load(&new_dir)?)));
+            fs::remove_dir_all(&temp_dir).ok();
+            result?
+        }
+        [old, new] if !old.starts_with("--") => {
+            (diff::load(Path::new(old))?, diff::load(Path::new(new))?)
+        }
+        _ => anyhow::bail!(usage),
+    };
+
+    println!();
+    if !diff::print_diff(&old, &new) {
+        println!("No changes");
+    }
+    Ok(())
+}
+
+// .dynamicfontをsrc/fonts.rsに追加するコードに変換
+fn import_fonts(paths: &[String]) -> anyhow::Result<()> {
+    if paths.is_empty() {
+        anyhow::bail!("Usage: import <.dynamicfont>...");
+    }
+
+    let source = vendor::font_source();
+    for path in paths {
+        let content = XnaContent::read(Path::new(path))?;
+        println!("{}", import::to_builder_code(&content.asset.font, &source));
+    }
+
+    Ok(())
+}
+
 #[allow(unused)]
 fn export_all_fonts() -> anyhow::Result<()> {
     let font_system_source = font_kit::source::SystemSource::new();
@@ -84,9 +312,32 @@ fn export_all_fonts() -> anyhow::Result<()> {
             writeln!(writer, "- family_name: \"{}\"", family_name)?;
             writeln!(writer, "  postscript_name: \"{}\"", postscript_name)?;
             writeln!(writer, "  full_name: \"{}\"", full_name)?;
-            writeln!(writer, "")?;
+            writeln!(writer)?;
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clear_output_dir() {
+        let dir = std::env::temp_dir().join(format!("font-clear-{}", std::process::id()));
+        fs::create_dir_all(dir.join("test").join("bmfont")).unwrap();
+        fs::write(dir.join("test").join("Test.dynamicfont"), "").unwrap();
+        fs::write(dir.join("test").join("report.xml"), "").unwrap();
+        fs::write(dir.join("test").join("bmfont").join("Test_0.png"), "").unwrap();
+
+        // このツール以外のファイルがある場合は削除しない
+        fs::write(dir.join("test").join("notes.png"), "").unwrap();
+        assert!(clear_output_dir(&dir).is_err());
+        assert!(dir.join("test").join("Test.dynamicfont").exists());
+
+        fs::remove_file(dir.join("test").join("notes.png")).unwrap();
+        clear_output_dir(&dir).unwrap();
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,358 @@
+use std::fmt::Display;
+
+use font_kit::font::Font;
+use serde::Serialize;
+
+use crate::{
+    font_info::{FontInfo, Os2Table},
+    xml::VerticalOffset,
+};
+
+// この値(em)以上異なる場合に警告する
+const VERTICAL_METRIC_TOLERANCE: f32 = 0.05;
+
+// フォールバックのサイズを合わせる基準
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[allow(unused)]
+pub enum SizeMatch {
+    XHeight,
+    CapHeight,
+    // 漢字の字面の高さ
+    IdeographicEmBox,
+}
+
+impl SizeMatch {
+    // OS/2に値が無い場合に計測するグリフ
+    fn reference_char(&self) -> char {
+        match self {
+            SizeMatch::XHeight => 'x',
+            SizeMatch::CapHeight => 'H',
+            SizeMatch::IdeographicEmBox => '国',
+        }
+    }
+
+    // em単位の高さを取得
+    pub fn measure(&self, font: &Font, os2: Option<&Os2Table>) -> Option<FontMetric> {
+        let units_per_em = font.metrics().units_per_em as f32;
+        let os2_value = match self {
+            SizeMatch::XHeight => os2.and_then(|os2| os2.x_height),
+            SizeMatch::CapHeight => os2.and_then(|os2| os2.cap_height),
+            SizeMatch::IdeographicEmBox => None,
+        };
+
+        if let Some(value) = os2_value.filter(|value| *value > 0) {
+            return Some(FontMetric {
+                value: value as f32 / units_per_em,
+                source: MetricSource::Os2,
+            });
+        }
+
+        let glyph = font.glyph_for_char(self.reference_char())?;
+        let bounds = font.typographic_bounds(glyph).ok()?;
+        let height = match self {
+            // ベースラインからの高さ
+            SizeMatch::XHeight | SizeMatch::CapHeight => bounds.max_y(),
+            SizeMatch::IdeographicEmBox => bounds.height(),
+        };
+        (height > 0.0).then(|| FontMetric {
+            value: height / units_per_em,
+            source: MetricSource::Glyph,
+        })
+    }
+}
+
+impl Display for SizeMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SizeMatch::XHeight => write!(f, "x-height"),
+            SizeMatch::CapHeight => write!(f, "cap-height"),
+            SizeMatch::IdeographicEmBox => write!(f, "ideographic em box"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum MetricSource {
+    #[serde(rename = "OS/2")]
+    Os2,
+    #[serde(rename = "Glyph")]
+    Glyph,
+}
+
+impl Display for MetricSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetricSource::Os2 => write!(f, "OS/2"),
+            MetricSource::Glyph => write!(f, "glyph"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontMetric {
+    pub value: f32,
+    pub source: MetricSource,
+}
+
+impl FontMetric {
+    // 基準のフォントと同じ高さになるサイズ (小数点以下2桁に丸める)
+    pub fn matched_size(&self, base: &FontMetric, base_size: f32) -> f32 {
+        (base_size * base.value / self.value * 100.0).round() / 100.0
+    }
+}
+
+// レポートに記録するフォント毎のサイズ
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchedSize {
+    #[serde(rename = "@FontName")]
+    pub font_name: String,
+    #[serde(rename = "@Match")]
+    pub size_match: SizeMatch,
+    #[serde(rename = "@Metric")]
+    pub metric: f32,
+    #[serde(rename = "@Source")]
+    pub source: MetricSource,
+    #[serde(rename = "@Size")]
+    pub size: f32,
+}
+
+impl Display for MatchedSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} ({} {:.3}em, {})",
+            self.font_name, self.size, self.size_match, self.metric, self.source
+        )
+    }
+}
+
+// 縦方向のメトリクス (em単位、ディセンダーはベースラインより下を正とする)
+#[derive(Debug, Clone, Serialize)]
+pub struct VerticalMetrics {
+    #[serde(rename = "@FontName")]
+    pub font_name: String,
+    #[serde(rename = "@Size")]
+    pub size: f32,
+    #[serde(rename = "@HheaAscender")]
+    pub hhea_ascender: f32,
+    #[serde(rename = "@HheaDescender")]
+    pub hhea_descender: f32,
+    #[serde(rename = "@HheaLineGap")]
+    pub hhea_line_gap: f32,
+    #[serde(rename = "@TypoAscender", skip_serializing_if = "Option::is_none")]
+    pub typo_ascender: Option<f32>,
+    #[serde(rename = "@TypoDescender", skip_serializing_if = "Option::is_none")]
+    pub typo_descender: Option<f32>,
+    #[serde(rename = "@WinAscent", skip_serializing_if = "Option::is_none")]
+    pub win_ascent: Option<f32>,
+    #[serde(rename = "@WinDescent", skip_serializing_if = "Option::is_none")]
+    pub win_descent: Option<f32>,
+    // 最初のフォントとのアセントの差 (ピクセル、正の場合は上にはみ出す)
+    #[serde(rename = "@BaselineOffset")]
+    pub baseline_offset: f32,
+}
+
+impl VerticalMetrics {
+    pub fn from_font(font_name: String, font: &Font, info: &FontInfo, size: f32) -> Self {
+        let units_per_em = font.metrics().units_per_em as f32;
+        let em = |value: f32| (value / units_per_em * 1000.0).round() / 1000.0;
+
+        // hheaが無い場合はfont-kitの値を使用
+        let (ascender, descender, line_gap) = match info.hhea {
+            Some(hhea) => (
+                hhea.ascender as f32,
+                -hhea.descender as f32,
+                hhea.line_gap as f32,
+            ),
+            None => {
+                let metrics = font.metrics();
+                (metrics.ascent, -metrics.descent, metrics.line_gap)
+            }
+        };
+
+        Self {
+            font_name,
+            size,
+            hhea_ascender: em(ascender),
+            hhea_descender: em(descender),
+            hhea_line_gap: em(line_gap),
+            typo_ascender: info.os2.map(|os2| em(os2.typo_ascender as f32)),
+            typo_descender: info.os2.map(|os2| em(-os2.typo_descender as f32)),
+            win_ascent: info.os2.map(|os2| em(os2.win_ascent as f32)),
+            win_descent: info.os2.map(|os2| em(os2.win_descent as f32)),
+            baseline_offset: 0.0,
+        }
+    }
+
+    fn ascent_pixels(&self) -> f32 {
+        self.hhea_ascender * self.size
+    }
+
+    // 比較する値の一覧
+    fn values(&self) -> [(&'static str, Option<f32>); 6] {
+        [
+            ("hhea ascender", Some(self.hhea_ascender)),
+            ("hhea descender", Some(self.hhea_descender)),
+            ("typo ascender", self.typo_ascender),
+            ("typo descender", self.typo_descender),
+            ("win ascent", self.win_ascent),
+            ("win descent", self.win_descent),
+        ]
+    }
+}
+
+// フォールバック間の縦方向のメトリクスの比較結果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct VerticalAlignment {
+    #[serde(rename = "@Current")]
+    pub current: VerticalOffset,
+    #[serde(rename = "@Recommended")]
+    pub recommended: VerticalOffset,
+    pub reason: String,
+    #[serde(rename = "Font")]
+    pub fonts: Vec<VerticalMetrics>,
+    #[serde(rename = "Warning")]
+    pub warnings: Vec<String>,
+}
+
+impl VerticalAlignment {
+    // 最初のフォントを基準に比較する
+    pub fn compare(mut fonts: Vec<VerticalMetrics>, current: VerticalOffset) -> Self {
+        let mut warnings = Vec::new();
+        let Some(base) = fonts.first().cloned() else {
+            return Self {
+                current,
+                recommended: current,
+                reason: String::new(),
+                fonts,
+                warnings,
+            };
+        };
+
+        for font in fonts.iter_mut().skip(1) {
+            font.baseline_offset =
+                ((font.ascent_pixels() - base.ascent_pixels()) * 100.0).round() / 100.0;
+
+            let mismatches = font
+                .values()
+                .iter()
+                .zip(base.values())
+                .filter_map(
+                    |((name, value), (_, base_value))| match (value, base_value) {
+                        (Some(value), Some(base_value))
+                            if (value - base_value).abs() >= VERTICAL_METRIC_TOLERANCE =>
+                        {
+                            Some(format!(
+                                "{} {:.3}em (base {:.3}em)",
+                                name, value, base_value
+                            ))
+                        }
+                        _ => None,
+                    },
+                )
+                .collect::<Vec<_>>();
+            if !mismatches.is_empty() {
+                warnings.push(format!(
+                    "{} differs from {}: {}",
+                    font.font_name,
+                    base.font_name,
+                    mismatches.join(", ")
+                ));
+            }
+        }
+
+        // DefaultFontAscentは最初のフォントのアセントで行を配置するため、
+        // それより高いフォールバックの文字は上が切れる
+        let overflow = fonts
+            .iter()
+            .skip(1)
+            .filter(|font| font.baseline_offset > 0.5)
+            .max_by(|a, b| a.baseline_offset.total_cmp(&b.baseline_offset));
+        let (recommended, reason) = match overflow {
+            Some(font) => (
+                VerticalOffset::MaxAscent,
+                format!(
+                    "{} is {:.1}px taller than {} and may be clipped with DefaultFontAscent",
+                    font.font_name, font.baseline_offset, base.font_name
+                ),
+            ),
+            None => (
+                VerticalOffset::DefaultFontAscent,
+                format!(
+                    "No fallback is taller than {}; DefaultFontAscent avoids extra line spacing",
+                    base.font_name
+                ),
+            ),
+        };
+
+        Self {
+            current,
+            recommended,
+            reason,
+            fonts,
+            warnings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matched_size() {
+        let base = FontMetric {
+            value: 0.5,
+            source: MetricSource::Os2,
+        };
+        let fallback = FontMetric {
+            value: 0.4,
+            source: MetricSource::Glyph,
+        };
+        assert_eq!(fallback.matched_size(&base, 16.0), 20.0);
+        assert_eq!(base.matched_size(&base, 16.0), 16.0);
+    }
+
+    fn vertical_metrics(font_name: &str, ascender: f32, size: f32) -> VerticalMetrics {
+        VerticalMetrics {
+            font_name: font_name.to_string(),
+            size,
+            hhea_ascender: ascender,
+            hhea_descender: 0.2,
+            hhea_line_gap: 0.0,
+            typo_ascender: Some(0.8),
+            typo_descender: Some(0.2),
+            win_ascent: None,
+            win_descent: None,
+            baseline_offset: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_vertical_alignment() {
+        let alignment = VerticalAlignment::compare(
+            vec![
+                vertical_metrics("Base", 0.9, 20.0),
+                vertical_metrics("Same", 0.92, 20.0),
+                vertical_metrics("Tall", 1.1, 20.0),
+            ],
+            VerticalOffset::DefaultFontAscent,
+        );
+        assert_eq!(alignment.fonts[1].baseline_offset, 0.4);
+        assert_eq!(alignment.fonts[2].baseline_offset, 4.0);
+        assert_eq!(alignment.warnings.len(), 1);
+        assert!(alignment.warnings[0].starts_with("Tall"));
+        assert_eq!(alignment.recommended, VerticalOffset::MaxAscent);
+
+        let alignment = VerticalAlignment::compare(
+            vec![
+                vertical_metrics("Base", 0.9, 20.0),
+                vertical_metrics("Same", 0.9, 20.0),
+            ],
+            VerticalOffset::MaxAscent,
+        );
+        assert!(alignment.warnings.is_empty());
+        assert_eq!(alignment.recommended, VerticalOffset::DefaultFontAscent);
+    }
+}
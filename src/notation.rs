@@ -0,0 +1,485 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use unicode_general_category::{get_general_category, GeneralCategory};
+
+use crate::types::{CharRange, CharRangeList};
+
+// 文字集合の表記
+//   U+3040-309F, U+30A0-U+30FF, 々, ー, a-z
+// カンマまたは改行で区切り、各要素は以下のいずれか
+//   U+XXXX          1文字 (16進数)
+//   U+XXXX-YYYY     範囲 (終端のU+は省略可)
+//   文字            1文字
+//   文字-文字       範囲
+// カンマは U+002C と書く
+
+const MAX_CODE_POINT: u32 = 0x10FFFF;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    // 要素が空
+    Empty,
+    // U+の後が16進数ではない
+    InvalidHex,
+    // U+10FFFFより大きい
+    OutOfRange,
+    // 終端が開始より小さい
+    ReversedRange,
+    // どの形式にも一致しない
+    UnexpectedToken,
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::Empty => write!(f, "empty range"),
+            ParseErrorKind::InvalidHex => write!(f, "invalid hexadecimal code point"),
+            ParseErrorKind::OutOfRange => write!(f, "code point is larger than U+10FFFF"),
+            ParseErrorKind::ReversedRange => write!(f, "range end is smaller than its start"),
+            ParseErrorKind::UnexpectedToken => {
+                write!(
+                    f,
+                    "expected a character, U+XXXX, or a range such as U+3040-309F"
+                )
+            }
+        }
+    }
+}
+
+// 解析に失敗した要素と位置 (行・列は1始まり、列は文字単位)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCharRangeError {
+    pub token: String,
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl Display for ParseCharRangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}: \"{}\"",
+            self.kind, self.line, self.column, self.token
+        )
+    }
+}
+
+impl std::error::Error for ParseCharRangeError {}
+
+// 1つの要素を解析
+fn parse_token(token: &str) -> Result<CharRange, ParseErrorKind> {
+    if token.is_empty() {
+        return Err(ParseErrorKind::Empty);
+    }
+    if let Some(hex) = strip_code_point_prefix(token) {
+        let (start, end) = match hex.split_once('-') {
+            Some((start, end)) => {
+                let end = end.trim();
+                let end = strip_code_point_prefix(end).unwrap_or(end);
+                (parse_hex(start.trim())?, parse_hex(end)?)
+            }
+            None => {
+                let c = parse_hex(hex)?;
+                (c, c)
+            }
+        };
+        return new_range(start, end);
+    }
+
+    let mut chars = token.chars();
+    match (chars.next(), chars.next(), chars.next(), chars.next()) {
+        (Some(c), None, _, _) => Ok(CharRange::new_single(c as u32)),
+        (Some(start), Some('-'), Some(end), None) => new_range(start as u32, end as u32),
+        _ => {
+            // 前後に空白がある範囲 (例: a - z)
+            match token.split_once('-').map(|(a, b)| (a.trim(), b.trim())) {
+                Some((start, end)) if is_single_char(start) && is_single_char(end) => {
+                    let start = start.chars().next().unwrap_or_default();
+                    let end = end.chars().next().unwrap_or_default();
+                    new_range(start as u32, end as u32)
+                }
+                _ => Err(ParseErrorKind::UnexpectedToken),
+            }
+        }
+    }
+}
+
+fn strip_code_point_prefix(token: &str) -> Option<&str> {
+    token
+        .strip_prefix("U+")
+        .or_else(|| token.strip_prefix("u+"))
+}
+
+fn is_single_char(s: &str) -> bool {
+    s.chars().count() == 1
+}
+
+fn parse_hex(hex: &str) -> Result<u32, ParseErrorKind> {
+    if hex.is_empty() || hex.len() > 8 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ParseErrorKind::InvalidHex);
+    }
+    let c = u32::from_str_radix(hex, 16).map_err(|_| ParseErrorKind::InvalidHex)?;
+    if c > MAX_CODE_POINT {
+        return Err(ParseErrorKind::OutOfRange);
+    }
+    Ok(c)
+}
+
+fn new_range(start: u32, end: u32) -> Result<CharRange, ParseErrorKind> {
+    if start > end {
+        return Err(ParseErrorKind::ReversedRange);
+    }
+    Ok(CharRange::new(start, end))
+}
+
+// 文字集合の表記を解析し、要素毎に範囲を返す
+fn parse_notation(s: &str) -> Result<Vec<CharRange>, ParseCharRangeError> {
+    let mut ranges = Vec::new();
+    for (line_index, line) in s.lines().enumerate() {
+        let mut column = 1;
+        for token in line.split(',') {
+            let trimmed = token.trim();
+            if !trimmed.is_empty() {
+                let leading = token.chars().count() - token.trim_start().chars().count();
+                let range = parse_token(trimmed).map_err(|kind| ParseCharRangeError {
+                    token: trimmed.to_string(),
+                    line: line_index + 1,
+                    column: column + leading,
+                    kind,
+                })?;
+                ranges.push(range);
+            }
+            // 区切りのカンマの分も進める
+            column += token.chars().count() + 1;
+        }
+    }
+    Ok(ranges)
+}
+
+impl FromStr for CharRange {
+    type Err = ParseCharRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ranges = parse_notation(s)?;
+        if ranges.len() == 1 {
+            return Ok(ranges.remove(0));
+        }
+        Err(ParseCharRangeError {
+            token: s.trim().to_string(),
+            line: 1,
+            column: 1,
+            kind: if ranges.is_empty() {
+                ParseErrorKind::Empty
+            } else {
+                ParseErrorKind::UnexpectedToken
+            },
+        })
+    }
+}
+
+impl FromStr for CharRangeList {
+    type Err = ParseCharRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(parse_notation(s)?.into_iter().collect())
+    }
+}
+
+// そのまま書いても読み取れる文字か (区切り文字・空白・制御文字等はU+で書く)
+fn is_literal(c: u32) -> bool {
+    let Some(c) = char::from_u32(c) else {
+        return false;
+    };
+    if c.is_whitespace() || matches!(c, ',' | '-') {
+        return false;
+    }
+    !matches!(
+        get_general_category(c),
+        GeneralCategory::Control
+            | GeneralCategory::Format
+            | GeneralCategory::Surrogate
+            | GeneralCategory::PrivateUse
+            | GeneralCategory::Unassigned
+            | GeneralCategory::NonspacingMark
+            | GeneralCategory::EnclosingMark
+            | GeneralCategory::SpacingMark
+            | GeneralCategory::LineSeparator
+            | GeneralCategory::ParagraphSeparator
+    )
+}
+
+// 表記の1要素に変換 (1文字は可能なら文字のまま、範囲はU+で書く)
+pub fn format_range(range: &CharRange) -> String {
+    if range.start == range.end {
+        match char::from_u32(range.start).filter(|_| is_literal(range.start)) {
+            Some(c) => c.to_string(),
+            None => format!("U+{:04X}", range.start),
+        }
+    } else {
+        format!("U+{:04X}-{:04X}", range.start, range.end)
+    }
+}
+
+impl CharRangeList {
+    // 表記に変換
+    pub fn to_notation(&self) -> String {
+        self.iter().map(format_range).collect::<Vec<_>>().join(", ")
+    }
+
+    // 文字の見本のテキストから作成 (#で始まる行と空白は無視)
+    pub fn from_sample_text(text: &str) -> Self {
+        text.lines()
+            .filter(|line| !line.starts_with('#'))
+            .flat_map(|line| line.chars())
+            .filter(|c| !c.is_whitespace())
+            .map(|c| CharRange::new_single(c as u32))
+            .collect()
+    }
+}
+
+impl Display for CharRangeList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_notation())
+    }
+}
+
+// 構造化した形式の1要素
+#[derive(Serialize, Deserialize)]
+struct StructuredRange {
+    #[serde(rename = "Start", alias = "start")]
+    start: CodePoint,
+    #[serde(rename = "End", alias = "end")]
+    end: CodePoint,
+}
+
+// 構造化した形式のコードポイント (数値または1文字の表記)
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum CodePoint {
+    Number(u32),
+    Notation(String),
+}
+
+impl CodePoint {
+    fn value<E: de::Error>(&self) -> Result<u32, E> {
+        match self {
+            CodePoint::Number(c) => Ok(*c),
+            CodePoint::Notation(s) => s.parse::<CharRange>().map_err(E::custom).and_then(|range| {
+                match range.start == range.end {
+                    true => Ok(range.start),
+                    false => Err(E::custom(format!("expected a single code point: {}", s))),
+                }
+            }),
+        }
+    }
+}
+
+impl StructuredRange {
+    fn to_range<E: de::Error>(&self) -> Result<CharRange, E> {
+        let (start, end) = (self.start.value()?, self.end.value()?);
+        if start > end {
+            return Err(E::custom(format!(
+                "{}: U+{:04X}-{:04X}",
+                ParseErrorKind::ReversedRange,
+                start,
+                end
+            )));
+        }
+        Ok(CharRange::new(start, end))
+    }
+}
+
+// 文字列の表記または構造化した形式
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RangeRepr {
+    Notation(String),
+    Structured(StructuredRange),
+}
+
+impl RangeRepr {
+    fn into_ranges<E: de::Error>(self) -> Result<Vec<CharRange>, E> {
+        match self {
+            RangeRepr::Notation(s) => parse_notation(&s).map_err(E::custom),
+            RangeRepr::Structured(range) => Ok(vec![range.to_range()?]),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RangeListRepr {
+    Notation(String),
+    Structured(Vec<RangeRepr>),
+}
+
+impl Serialize for CharRange {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_range(self))
+    }
+}
+
+impl<'de> Deserialize<'de> for CharRange {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match RangeRepr::deserialize(deserializer)? {
+            RangeRepr::Notation(s) => s.parse().map_err(de::Error::custom),
+            RangeRepr::Structured(range) => range.to_range(),
+        }
+    }
+}
+
+impl Serialize for CharRangeList {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_notation())
+    }
+}
+
+impl<'de> Deserialize<'de> for CharRangeList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let ranges = match RangeListRepr::deserialize(deserializer)? {
+            RangeListRepr::Notation(s) => parse_notation(&s).map_err(de::Error::custom)?,
+            RangeListRepr::Structured(reprs) => {
+                let mut ranges = Vec::new();
+                for repr in reprs {
+                    ranges.extend(repr.into_ranges()?);
+                }
+                ranges
+            }
+        };
+        Ok(ranges.into_iter().collect())
+    }
+}
+
+// #[serde(with = "crate::notation::structured")] で {Start, End} の並びとして書き出す
+#[allow(unused)]
+pub mod structured {
+    use serde::{ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+
+    use super::{CodePoint, StructuredRange};
+    use crate::types::CharRangeList;
+
+    pub fn serialize<S: Serializer>(
+        list: &CharRangeList,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(list.ranges().len()))?;
+        for range in list {
+            seq.serialize_element(&StructuredRange {
+                start: CodePoint::Number(range.start),
+                end: CodePoint::Number(range.end),
+            })?;
+        }
+        seq.end()
+    }
+
+    // 読み込みはどちらの形式も受け付ける
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<CharRangeList, D::Error> {
+        CharRangeList::deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_notation() {
+        let list: CharRangeList = "U+3040-309F, U+30A0-U+30FF, 々, ー\na-c, u+0041"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            list.ranges(),
+            &[
+                CharRange::new(0x41, 0x41),
+                CharRange::new('a' as u32, 'c' as u32),
+                CharRange::new(0x3005, 0x3005),
+                CharRange::new(0x3040, 0x30FF),
+            ]
+        );
+        assert_eq!(
+            "-".parse::<CharRange>().unwrap(),
+            CharRange::new_single(0x2D)
+        );
+        assert_eq!(
+            "a - z".parse::<CharRange>().unwrap(),
+            CharRange::new(0x61, 0x7A)
+        );
+        assert!("".parse::<CharRangeList>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let error = "U+3040-309F, abc".parse::<CharRangeList>().unwrap_err();
+        assert_eq!(error.token, "abc");
+        assert_eq!((error.line, error.column), (1, 14));
+        assert_eq!(error.kind, ParseErrorKind::UnexpectedToken);
+
+        let error = "a\n  ア, U+30FF-30A0".parse::<CharRangeList>().unwrap_err();
+        assert_eq!(error.token, "U+30FF-30A0");
+        assert_eq!((error.line, error.column), (2, 6));
+        assert_eq!(error.kind, ParseErrorKind::ReversedRange);
+
+        let error = "U+GGGG".parse::<CharRangeList>().unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::InvalidHex);
+        let error = "U+110000".parse::<CharRangeList>().unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::OutOfRange);
+        assert!("a, b".parse::<CharRange>().is_err());
+    }
+
+    #[test]
+    fn test_format_notation() {
+        let list = CharRangeList::from(vec![
+            CharRange::new(0x3040, 0x30FF),
+            CharRange::new_single(0x3005),
+            CharRange::new_single(',' as u32),
+            CharRange::new_single(0x0A),
+            CharRange::new_single(0x0301),
+        ]);
+        let notation = list.to_notation();
+        assert_eq!(notation, "U+000A, U+002C, U+0301, 々, U+3040-30FF");
+        assert_eq!(notation.parse::<CharRangeList>().unwrap(), list);
+    }
+
+    #[test]
+    fn test_from_sample_text() {
+        let list = CharRangeList::from_sample_text("# comment\nあい う\nあa\n");
+        assert_eq!(list.to_notation(), "a, あ, い, う");
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        chars: CharRangeList,
+        #[serde(with = "structured")]
+        structured: CharRangeList,
+    }
+
+    #[test]
+    fn test_serde() {
+        let chars = CharRangeList::from(vec![
+            CharRange::new(0x3040, 0x309F),
+            CharRange::new_single(0x3005),
+        ]);
+        let config = Config {
+            chars: chars.clone(),
+            structured: chars.clone(),
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(
+            json,
+            r#"{"chars":"々, U+3040-309F","structured":[{"Start":12293,"End":12293},{"Start":12352,"End":12447}]}"#
+        );
+        assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), config);
+
+        // どちらのフィールドも両方の形式を受け付ける
+        let json = r#"{"chars":["U+3040-309F",{"start":"々","end":12293}],"structured":"々, U+3040-309F"}"#;
+        assert_eq!(serde_json::from_str::<Config>(json).unwrap(), config);
+
+        let error = serde_json::from_str::<CharRangeList>(r#""a, U+30ZZ""#).unwrap_err();
+        assert!(error.to_string().contains("\"U+30ZZ\""));
+        assert!(error.to_string().contains("column 4"));
+    }
+}
@@ -0,0 +1,59 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    atlas::GlyphAtlas,
+    bmfont::{BmFont, BmFontFormat},
+    spritefont::{SpriteFont, SpriteFontMode},
+    vendor::font_source,
+    xml::DynamicFont,
+    xnb::{SpriteFontXnb, HIDEF_MAX_TEXTURE_SIZE},
+};
+
+// .dynamicfontに加えて書き出す形式
+#[derive(Debug, Clone)]
+#[allow(unused)]
+pub enum FontOutput {
+    // MonoGame/FNAの.spritefont
+    SpriteFont(SpriteFontMode),
+    // MonoGame/FNAのSpriteFontの.xnb (spritefontフォルダに書き出す)
+    SpriteFontXnb,
+    // AngelCode BMFontの.fntとPNG (bmfontフォルダに書き出す)
+    BmFont(BmFontFormat),
+}
+
+impl FontOutput {
+    // 書き出したファイルと警告を返す
+    pub fn write(
+        &self,
+        font: &DynamicFont,
+        directory: &Path,
+    ) -> anyhow::Result<(Vec<PathBuf>, Vec<String>)> {
+        match self {
+            FontOutput::SpriteFont(mode) => {
+                let (sprite_fonts, warnings) = SpriteFont::from_dynamic_font(font, *mode);
+                let paths = sprite_fonts
+                    .iter()
+                    .map(|sprite_font| sprite_font.write(directory))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Ok((paths, warnings))
+            }
+            FontOutput::SpriteFontXnb => {
+                let atlas = GlyphAtlas::build(font, &font_source(), HIDEF_MAX_TEXTURE_SIZE)?;
+                let xnb = SpriteFontXnb {
+                    file_name: &font.file_name,
+                    atlas: &atlas,
+                    spacing: font.spacing,
+                    use_kerning: font.use_kerning,
+                    default_character: Some(font.default_character),
+                };
+                let path = xnb.write(&directory.join("spritefont"))?;
+                Ok((vec![path], atlas.warnings))
+            }
+            FontOutput::BmFont(format) => {
+                let bmfont = BmFont::build(font, &font_source())?;
+                let paths = bmfont.write(&directory.join("bmfont"), *format)?;
+                Ok((paths, bmfont.atlas.warnings))
+            }
+        }
+    }
+}
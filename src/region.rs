@@ -0,0 +1,183 @@
+use serde::Serialize;
+use unicode_general_category::{get_general_category, GeneralCategory};
+
+use crate::types::{CharRange, CharRangeList, Counter};
+
+const SURROGATES: CharRange = CharRange {
+    start: 0xD800,
+    end: 0xDFFF,
+};
+
+// 同じフォントの範囲の間の隙間を埋めてCharacterRegionの数を減らす
+// 隙間は他のフォントが使用しておらず、そのフォントのcmapにも含まれない文字のみ
+#[derive(Debug, Clone, Copy)]
+pub struct RegionMerge {
+    // 埋める隙間の最大の文字数
+    max_gap: u32,
+    // 追加してよい、Unicodeで割り当て済みの文字の合計 (未割り当てのコードポイントは制限しない)
+    max_extra_glyphs: usize,
+}
+
+impl RegionMerge {
+    #[allow(unused)]
+    pub fn new(max_gap: u32) -> Self {
+        Self {
+            max_gap,
+            max_extra_glyphs: 0,
+        }
+    }
+
+    // グリフの無い文字を追加してでも範囲を減らす場合に指定
+    #[allow(unused)]
+    pub fn max_extra_glyphs(mut self, max_extra_glyphs: usize) -> Self {
+        self.max_extra_glyphs = max_extra_glyphs;
+        self
+    }
+
+    // include_charsの隙間を埋める
+    pub fn apply(
+        &self,
+        include_chars: &mut [CharRangeList],
+        cmap_chars: &[CharRangeList],
+    ) -> RegionReport {
+        let before = region_count(include_chars);
+
+        // 埋められる隙間 (フォントの番号, 隙間, グリフの無い文字数)
+        let mut gaps = Vec::new();
+        for (index, (chars, cmap)) in include_chars.iter().zip(cmap_chars).enumerate() {
+            for pair in chars.ranges().windows(2) {
+                let gap = CharRange::new(pair[0].end + 1, pair[1].start - 1);
+                if gap.count() > self.max_gap as usize {
+                    continue;
+                }
+                let gap_list = CharRangeList::from(vec![gap]);
+                if !gap_list.intersection(cmap).is_empty()
+                    || !gap_list
+                        .intersection(&CharRangeList::from(vec![SURROGATES]))
+                        .is_empty()
+                {
+                    continue;
+                }
+                gaps.push((index, gap, assigned_count(gap)));
+            }
+        }
+        // 未割り当てのコードポイントのみの隙間から、短い順に埋める
+        gaps.sort_by_key(|(_, gap, cost)| (*cost, gap.count()));
+
+        let mut extra_chars = 0;
+        let mut extra_glyphs = 0;
+        for (index, gap, cost) in gaps {
+            if extra_glyphs + cost > self.max_extra_glyphs {
+                continue;
+            }
+            // 他のフォントが使用している文字は埋めない
+            let gap_list = CharRangeList::from(vec![gap]);
+            let claimed = include_chars
+                .iter()
+                .any(|chars| !chars.intersection(&gap_list).is_empty());
+            if claimed {
+                continue;
+            }
+            include_chars[index].add_range(gap);
+            extra_chars += gap.count();
+            extra_glyphs += cost;
+        }
+
+        RegionReport {
+            before,
+            after: region_count(include_chars),
+            extra_chars,
+            extra_glyphs,
+        }
+    }
+}
+
+fn region_count(include_chars: &[CharRangeList]) -> usize {
+    include_chars.iter().map(|chars| chars.ranges().len()).sum()
+}
+
+// Unicodeで割り当て済みの文字数 (グリフが無く、DefaultCharacterの代わりに空白等が表示される)
+fn assigned_count(range: CharRange) -> usize {
+    (range.start..=range.end)
+        .filter(|c| {
+            char::from_u32(*c)
+                .is_some_and(|c| get_general_category(c) != GeneralCategory::Unassigned)
+        })
+        .count()
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RegionReport {
+    #[serde(rename = "@Before")]
+    pub before: usize,
+    #[serde(rename = "@After")]
+    pub after: usize,
+    // 隙間を埋めるために追加した文字数
+    #[serde(rename = "@ExtraChars")]
+    pub extra_chars: usize,
+    // そのうちUnicodeで割り当て済みの文字数
+    #[serde(rename = "@ExtraGlyphs")]
+    pub extra_glyphs: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(notation: &str) -> CharRangeList {
+        notation.parse().unwrap()
+    }
+
+    #[test]
+    fn test_merge_unassigned_gaps() {
+        // U+0378-0379、U+0380-0383、U+038B、U+038DはUnicodeで未割り当て
+        let mut include_chars = vec![
+            list("U+0370-0377, U+037A-037F, U+0384-038A, U+0390-03A1"),
+            list(""),
+        ];
+        let cmap_chars = include_chars.clone();
+
+        let report = RegionMerge::new(8).apply(&mut include_chars, &cmap_chars);
+        assert_eq!(include_chars[0], list("U+0370-038A, U+0390-03A1"));
+        assert_eq!((report.before, report.after), (4, 2));
+        assert_eq!((report.extra_chars, report.extra_glyphs), (6, 0));
+
+        // 割り当て済みの文字を含む隙間は上限まで埋める
+        let report = RegionMerge::new(8)
+            .max_extra_glyphs(2)
+            .apply(&mut include_chars, &cmap_chars);
+        assert_eq!(report.after, 2);
+        let report = RegionMerge::new(8)
+            .max_extra_glyphs(3)
+            .apply(&mut include_chars, &cmap_chars);
+        assert_eq!(include_chars[0], list("U+0370-03A1"));
+        assert_eq!((report.extra_chars, report.extra_glyphs), (5, 3));
+    }
+
+    #[test]
+    fn test_merge_blocked_gaps() {
+        // 隙間を他のフォントが使用している
+        let mut include_chars = vec![list("a-c, x-z"), list("m")];
+        let cmap_chars = vec![list("a-c, x-z"), list("m")];
+        RegionMerge::new(100)
+            .max_extra_glyphs(100)
+            .apply(&mut include_chars, &cmap_chars);
+        assert_eq!(include_chars[0], list("a-c, x-z"));
+
+        // 隙間の文字がcmapにある (フィルター等で除外した)
+        let mut include_chars = vec![list("a-c, x-z")];
+        let cmap_chars = vec![list("a-z")];
+        RegionMerge::new(100)
+            .max_extra_glyphs(100)
+            .apply(&mut include_chars, &cmap_chars);
+        assert_eq!(include_chars[0], list("a-c, x-z"));
+
+        // 隙間が上限より長い
+        let mut include_chars = vec![list("a-c, x-z")];
+        let cmap_chars = include_chars.clone();
+        RegionMerge::new(10)
+            .max_extra_glyphs(100)
+            .apply(&mut include_chars, &cmap_chars);
+        assert_eq!(include_chars[0], list("a-c, x-z"));
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{
+    budget::BudgetReport,
+    charset::PresetCoverage,
+    corpus::CorpusReport,
+    explain::FontAttribution,
+    glyph::ExcludedGlyphs,
+    license::FontLicense,
+    metrics::{MatchedSize, VerticalAlignment},
+    region::RegionReport,
+};
+
+// バンドル毎のビルドレポート
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BuildReport {
+    #[serde(rename = "Font")]
+    pub fonts: Vec<FontReport>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct FontReport {
+    #[serde(rename = "@FileName")]
+    pub file_name: String,
+    #[serde(rename = "License")]
+    pub licenses: Vec<FontLicense>,
+    #[serde(rename = "Coverage")]
+    pub coverage: Vec<PresetCoverage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub corpus: Option<CorpusReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<BudgetReport>,
+    #[serde(rename = "MatchedSize", skip_serializing_if = "Vec::is_empty")]
+    pub matched_sizes: Vec<MatchedSize>,
+    #[serde(rename = "VerticalMetrics", skip_serializing_if = "Option::is_none")]
+    pub vertical: Option<VerticalAlignment>,
+    #[serde(rename = "Excluded", skip_serializing_if = "Vec::is_empty")]
+    pub excluded_glyphs: Vec<ExcludedGlyphs>,
+    // フォールバック毎に使用する文字
+    #[serde(rename = "Assignment")]
+    pub attribution: Vec<FontAttribution>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regions: Option<RegionReport>,
+    // .spritefont等に書き出せなかった文字等
+    #[serde(rename = "OutputWarning", skip_serializing_if = "Vec::is_empty")]
+    pub output_warnings: Vec<String>,
+}
+
+impl BuildReport {
+    fn to_xml(&self) -> anyhow::Result<String> {
+        let mut buffer = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+
+        let mut ser = quick_xml::se::Serializer::new(&mut buffer);
+        ser.indent(' ', 2);
+        self.serialize(ser)?;
+
+        Ok(buffer)
+    }
+
+    pub fn write(&self, directory: &Path) -> anyhow::Result<PathBuf> {
+        let content = self.to_xml()?;
+        fs::create_dir_all(directory)?;
+        let path = directory.join("report.xml");
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+
+    // コンソールにサマリーを表示
+    pub fn print_summary(&self) {
+        for font in &self.fonts {
+            println!("{}:", font.file_name);
+            for license in &font.licenses {
+                println!("  {}", license);
+            }
+            let coverage = font
+                .coverage
+                .iter()
+                .filter(|c| c.covered > 0)
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>();
+            if !coverage.is_empty() {
+                println!("  Coverage: {}", coverage.join(", "));
+            }
+            if let Some(corpus) = &font.corpus {
+                println!(
+                    "  Corpus: {} chars, {} rare, {} uncovered ranges",
+                    corpus.chars,
+                    corpus.rare.len(),
+                    corpus.uncovered.len()
+                );
+                for range in &corpus.uncovered {
+                    println!("    uncovered: {}", range);
+                }
+            }
+            for size in &font.matched_sizes {
+                println!("  Size: {}", size);
+            }
+            if let Some(vertical) = &font.vertical {
+                for warning in &vertical.warnings {
+                    println!("  Warning: {}", warning);
+                }
+                if vertical.current != vertical.recommended {
+                    println!(
+                        "  VerticalOffset: {:?} is recommended ({})",
+                        vertical.recommended, vertical.reason
+                    );
+                }
+            }
+            for excluded in &font.excluded_glyphs {
+                println!(
+                    "  Excluded: {} ranges from {} ({})",
+                    excluded.ranges.len(),
+                    excluded.font_name,
+                    excluded.reason
+                );
+            }
+            if let Some(regions) = &font.regions {
+                println!(
+                    "  Regions: {} -> {} (+{} chars, {} without glyphs)",
+                    regions.before, regions.after, regions.extra_chars, regions.extra_glyphs
+                );
+            }
+            for warning in &font.output_warnings {
+                println!("  Warning: {}", warning);
+            }
+            if let Some(budget) = font.budget.as_ref().filter(|b| b.dropped_count > 0) {
+                println!(
+                    "  Budget: {} chars dropped to fit {} chars",
+                    budget.dropped_count, budget.max_chars
+                );
+            }
+        }
+    }
+}
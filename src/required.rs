@@ -0,0 +1,135 @@
+use std::{fmt::Display, fs, path::PathBuf};
+
+use anyhow::Context as _;
+
+use crate::{
+    charset::Preset,
+    types::{CharRange, CharRangeList, Counter},
+};
+
+// ビルド結果に必ず含まれていなければならない文字
+#[derive(Debug, Clone)]
+pub enum RequiredChars {
+    Preset(Preset),
+    // 文字の見本のテキストファイル (#で始まる行と空白は無視)
+    File(PathBuf),
+    Chars(CharRangeList),
+}
+
+impl RequiredChars {
+    #[allow(unused)]
+    pub fn file<T: Into<PathBuf>>(path: T) -> Self {
+        RequiredChars::File(path.into())
+    }
+
+    pub fn chars(&self) -> anyhow::Result<CharRangeList> {
+        match self {
+            RequiredChars::Preset(preset) => Ok(preset.chars()),
+            RequiredChars::File(path) => {
+                let content = fs::read_to_string(path).with_context(|| {
+                    format!("Failed to read required characters: {}", path.display())
+                })?;
+                Ok(CharRangeList::from_sample_text(&content))
+            }
+            RequiredChars::Chars(chars) => Ok(chars.clone()),
+        }
+    }
+}
+
+impl Display for RequiredChars {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequiredChars::Preset(preset) => write!(f, "Preset({})", preset),
+            RequiredChars::File(path) => write!(f, "File({})", path.display()),
+            RequiredChars::Chars(chars) => write!(f, "Chars({})", chars),
+        }
+    }
+}
+
+impl From<Preset> for RequiredChars {
+    fn from(preset: Preset) -> Self {
+        RequiredChars::Preset(preset)
+    }
+}
+
+impl From<CharRangeList> for RequiredChars {
+    fn from(chars: CharRangeList) -> Self {
+        RequiredChars::Chars(chars)
+    }
+}
+
+impl From<CharRange> for RequiredChars {
+    fn from(range: CharRange) -> Self {
+        RequiredChars::Chars(CharRangeList::from(vec![range]))
+    }
+}
+
+impl From<char> for RequiredChars {
+    fn from(c: char) -> Self {
+        CharRange::new_single(c as u32).into()
+    }
+}
+
+// ビルド結果に含まれていない必須の文字があれば例外
+pub fn check_required_chars(
+    required: &[(RequiredChars, CharRangeList)],
+    all_chars: &CharRangeList,
+) -> anyhow::Result<()> {
+    let missing = required
+        .iter()
+        .map(|(source, chars)| (source, chars.difference(all_chars)))
+        .filter(|(_, missing)| !missing.is_empty())
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let list = missing
+        .iter()
+        .map(|(source, missing)| {
+            format!(
+                "  {} ({} missing): {}",
+                source,
+                missing.count(),
+                missing
+                    .iter()
+                    .map(|range| range.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    anyhow::bail!(
+        "Required characters are missing:\n{}\n\
+        Check that every fallback font is installed or vendored and that no filter excludes them.",
+        list
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_required_chars() {
+        let all_chars = CharRangeList::from(vec![CharRange::new(0x20, 0x7E)]);
+        let required = vec![
+            (
+                RequiredChars::from('a'),
+                CharRangeList::from(vec![CharRange::new_single(0x61)]),
+            ),
+            (
+                RequiredChars::from(CharRange::new(0x30FC, 0x30FC)),
+                "ー, 〜, A-C".parse::<CharRangeList>().unwrap(),
+            ),
+        ];
+        let error = check_required_chars(&required, &all_chars)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Chars(ー) (2 missing): 〜(U+301C), ー(U+30FC)"));
+        assert!(!error.contains("Chars(a)"));
+
+        assert!(check_required_chars(&required[..1], &all_chars).is_ok());
+    }
+}
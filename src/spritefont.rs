@@ -0,0 +1,262 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use quick_xml::{
+    events::{BytesDecl, Event},
+    Writer,
+};
+
+use crate::{
+    import::group_regions,
+    types::{CharRangeList, Counter},
+    xml::{write_char_reference, write_text, DynamicFont, FontStyle},
+};
+
+// .spritefontにはCharacterRegion毎のフォントが無いため、フォールバックのフォントの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(unused)]
+pub enum SpriteFontMode {
+    // 基準のフォントの文字のみを書き出し、フォールバックの文字は警告
+    Warn,
+    // フォント・サイズ・スタイル毎に別の.spritefontに分割
+    Split,
+}
+
+// MonoGame/FNAのFontDescription (.spritefont)
+#[derive(Debug, Clone)]
+pub struct SpriteFont {
+    pub file_name: String,
+    pub font_name: String,
+    pub size: f32,
+    pub spacing: f32,
+    pub use_kerning: bool,
+    pub style: FontStyle,
+    // 文字集合に含まれない場合は省略
+    pub default_character: Option<char>,
+    pub chars: CharRangeList,
+}
+
+impl SpriteFont {
+    // DynamicFontを.spritefontに変換 (警告も返す)
+    pub fn from_dynamic_font(font: &DynamicFont, mode: SpriteFontMode) -> (Vec<Self>, Vec<String>) {
+        let mut sprite_fonts = Vec::new();
+        // ビットマップフォントは.spritefontで参照できない
+        let mut warnings = font
+            .bitmap_overrides
+            .iter()
+            .filter(|bitmap_override| !bitmap_override.chars.is_empty())
+            .map(|bitmap_override| {
+                format!(
+                    "Glyphs of {} chars from bitmap font {} are not used in {}.spritefont",
+                    bitmap_override.chars.count(),
+                    bitmap_override.font.face,
+                    font.file_name
+                )
+            })
+            .collect::<Vec<_>>();
+        for (index, imported) in group_regions(font).into_iter().enumerate() {
+            if index > 0 && mode == SpriteFontMode::Warn {
+                warnings.push(format!(
+                    "{} chars from {} are not written to {}.spritefont",
+                    imported.chars.count(),
+                    imported.font_name,
+                    font.file_name
+                ));
+                continue;
+            }
+            if imported.chars.is_empty() {
+                continue;
+            }
+
+            let size = imported.size.unwrap_or(font.size);
+            let style = imported.style.unwrap_or(font.style);
+            let file_name = match index {
+                0 => font.file_name.clone(),
+                _ => split_file_name(
+                    &font.file_name,
+                    &imported.font_name,
+                    imported.size,
+                    imported.style,
+                ),
+            };
+            let default_character =
+                Some(font.default_character).filter(|c| imported.chars.contains(*c as u32));
+            sprite_fonts.push(Self {
+                file_name,
+                font_name: imported.font_name,
+                size,
+                spacing: font.spacing,
+                use_kerning: font.use_kerning,
+                style,
+                default_character,
+                chars: imported.chars,
+            });
+        }
+        (sprite_fonts, warnings)
+    }
+
+    pub fn to_xml(&self) -> anyhow::Result<String> {
+        let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
+        writer
+            .create_element("XnaContent")
+            .with_attribute((
+                "xmlns:Graphics",
+                "Microsoft.Xna.Framework.Content.Pipeline.Graphics",
+            ))
+            .write_inner_content(|writer| {
+                writer
+                    .create_element("Asset")
+                    .with_attribute(("Type", "Graphics:FontDescription"))
+                    .write_inner_content(|writer| {
+                        write_text(writer, "FontName", &self.font_name)?;
+                        write_text(writer, "Size", &self.size.to_string())?;
+                        write_text(writer, "Spacing", &self.spacing.to_string())?;
+                        write_text(writer, "UseKerning", &self.use_kerning.to_string())?;
+                        write_text(writer, "Style", style_name(self.style))?;
+                        if let Some(c) = self.default_character {
+                            write_char_reference(writer, "DefaultCharacter", c)?;
+                        }
+                        writer
+                            .create_element("CharacterRegions")
+                            .write_inner_content(|writer| {
+                                for range in self.chars.iter() {
+                                    writer
+                                        .create_element("CharacterRegion")
+                                        .write_inner_content(|writer| {
+                                            write_char_reference(
+                                                writer,
+                                                "Start",
+                                                to_char(range.start),
+                                            )?;
+                                            write_char_reference(
+                                                writer,
+                                                "End",
+                                                to_char(range.end),
+                                            )?;
+                                            Ok(())
+                                        })?;
+                                }
+                                Ok(())
+                            })?;
+                        Ok(())
+                    })?;
+                Ok(())
+            })?;
+
+        Ok(String::from_utf8(writer.into_inner())?)
+    }
+
+    pub fn write(&self, directory: &Path) -> anyhow::Result<PathBuf> {
+        let content = self.to_xml()?;
+        fs::create_dir_all(directory)?;
+        let path = directory.join(format!("{}.spritefont", self.file_name));
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+}
+
+// FontDescriptionStyleはフラグのため、BoldItalicは"Bold, Italic"
+fn style_name(style: FontStyle) -> &'static str {
+    match style {
+        FontStyle::Regular => "Regular",
+        FontStyle::Bold => "Bold",
+        FontStyle::Italic => "Italic",
+        FontStyle::BoldItalic => "Bold, Italic",
+    }
+}
+
+// CharacterRegionはcharに変換済みのため、範囲は常に有効な文字
+fn to_char(c: u32) -> char {
+    char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+// 分割したフォントのファイル名 (例: Test_NotoSansJP_13_2_Bold)
+fn split_file_name(
+    file_name: &str,
+    font_name: &str,
+    size: Option<f32>,
+    style: Option<FontStyle>,
+) -> String {
+    let mut name = format!("{}_", file_name);
+    name.extend(font_name.chars().filter(|c| c.is_alphanumeric()));
+    if let Some(size) = size {
+        name.push_str(&format!("_{}", size).replace('.', "_"));
+    }
+    if let Some(style) = style {
+        name.push_str(&format!("_{:?}", style));
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xml::{CharacterRegion, VerticalOffset};
+
+    fn test_font() -> DynamicFont {
+        let mut kana = CharacterRegion::new('ぁ', 'ゖ');
+        kana.font_name = Some("Noto Sans JP".to_string());
+        kana.size = Some(13.2);
+        DynamicFont {
+            file_name: "Test".to_string(),
+            font_name: "Tom & Jerry".to_string(),
+            size: 12.0,
+            spacing: 1.0,
+            use_kerning: true,
+            style: FontStyle::BoldItalic,
+            default_character: '*',
+            vertical_offset: VerticalOffset::DefaultFontAscent,
+            character_regions: vec![CharacterRegion::new(' ', '~'), kana].into(),
+            bitmap_overrides: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_sprite_font_xml() {
+        let (sprite_fonts, warnings) =
+            SpriteFont::from_dynamic_font(&test_font(), SpriteFontMode::Warn);
+        assert_eq!(sprite_fonts.len(), 1);
+        assert_eq!(
+            warnings,
+            vec!["86 chars from Noto Sans JP are not written to Test.spritefont"]
+        );
+
+        let expected = r#"<?xml version="1.0" encoding="utf-8"?>
+<XnaContent xmlns:Graphics="Microsoft.Xna.Framework.Content.Pipeline.Graphics">
+  <Asset Type="Graphics:FontDescription">
+    <FontName>Tom &amp; Jerry</FontName>
+    <Size>12</Size>
+    <Spacing>1</Spacing>
+    <UseKerning>true</UseKerning>
+    <Style>Bold, Italic</Style>
+    <DefaultCharacter>&#x2A;</DefaultCharacter>
+    <CharacterRegions>
+      <CharacterRegion>
+        <Start>&#x20;</Start>
+        <End>&#x7E;</End>
+      </CharacterRegion>
+    </CharacterRegions>
+  </Asset>
+</XnaContent>"#;
+        assert_eq!(sprite_fonts[0].to_xml().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_split_sprite_font() {
+        let (sprite_fonts, warnings) =
+            SpriteFont::from_dynamic_font(&test_font(), SpriteFontMode::Split);
+        assert!(warnings.is_empty());
+        assert_eq!(sprite_fonts.len(), 2);
+        assert_eq!(sprite_fonts[0].file_name, "Test");
+        let kana = &sprite_fonts[1];
+        assert_eq!(kana.file_name, "Test_NotoSansJP_13_2");
+        assert_eq!(kana.font_name, "Noto Sans JP");
+        assert_eq!((kana.size, kana.style), (13.2, FontStyle::BoldItalic));
+        // 分割したフォントに無いDefaultCharacterは省略
+        assert_eq!(kana.default_character, None);
+        assert!(!kana.to_xml().unwrap().contains("DefaultCharacter"));
+    }
+}
@@ -1,7 +1,7 @@
 use std::{borrow::Cow, fmt::Display, path::PathBuf};
 
 use font::characters::Character;
-use font_kit::source::SystemSource;
+use font_kit::source::Source;
 
 pub trait Counter {
     fn count(&self) -> usize;
@@ -44,7 +44,7 @@ impl<'a> Display for FontName<'a> {
 impl<'a> FontName<'a> {
     pub fn get_font_handle(
         &self,
-        source: &SystemSource,
+        source: &dyn Source,
     ) -> Result<font_kit::handle::Handle, font_kit::error::SelectionError> {
         match self {
             FontName::Family(name) => source
@@ -63,7 +63,7 @@ impl<'a> FontName<'a> {
         }
     }
 
-    pub fn into_bundle(&self, source: &SystemSource) -> anyhow::Result<FontNameBundle<'a>> {
+    pub fn to_bundle(&self, source: &dyn Source) -> anyhow::Result<FontNameBundle<'a>> {
         let handle = self.get_font_handle(source)?;
         let font = handle.load()?;
         let family = font.family_name().to_string();
@@ -87,11 +87,12 @@ pub struct FontNameBundle<'a> {
 impl FontNameBundle<'_> {
     pub fn get_font_handle(
         &self,
-        source: &SystemSource,
+        source: &dyn Source,
     ) -> Result<font_kit::handle::Handle, font_kit::error::SelectionError> {
         FontName::Full(self.full.clone()).get_font_handle(source)
     }
-    pub fn path(&self, source: &SystemSource) -> anyhow::Result<PathBuf> {
+
+    pub fn path(&self, source: &dyn Source) -> anyhow::Result<PathBuf> {
         if let font_kit::handle::Handle::Path {
             path,
             font_index: _,
@@ -104,6 +105,21 @@ impl FontNameBundle<'_> {
     }
 }
 
+impl FontNameBundle<'_> {
+    // cmapに含まれる文字を取得
+    pub fn characters(&self, source: &dyn Source) -> anyhow::Result<CharRangeList> {
+        let font_file = font::File::open(self.path(source)?)?;
+
+        let mut supported_chars = Vec::new();
+
+        for mut f in font_file.fonts {
+            supported_chars.extend(f.characters()?);
+        }
+
+        Ok(CharRangeList::from(supported_chars))
+    }
+}
+
 impl Display for FontNameBundle<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -131,13 +147,13 @@ impl CharRange {
 }
 
 impl CharRange {
-    fn contains(&self, c: u32) -> bool {
+    pub fn contains(&self, c: u32) -> bool {
         self.start <= c && c <= self.end
     }
 
     // 範囲が隣接している、または重複しているかを判定
     fn merges_with(&self, other: &CharRange) -> bool {
-        self.end + 1 >= other.start && self.start <= other.end + 1
+        self.end.saturating_add(1) >= other.start && self.start <= other.end.saturating_add(1)
     }
 
     // 2つの範囲をマージ
@@ -154,7 +170,7 @@ impl CharRange {
         let mut result = Vec::new();
 
         // 範囲が完全に重なっている場合は何も残らない
-        if self.start >= other.end + 1 || self.end as i64 <= other.start as i64 - 1 {
+        if self.start > other.end || (self.end as i64) < other.start as i64 {
             result.push(*self); // 重なっていなければ元の範囲をそのまま残す
         } else {
             // 左部分の残り範囲
@@ -206,27 +222,21 @@ impl From<Character> for CharRange {
 
 impl Display for CharRange {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // サロゲート等のcharで表せない値はコードポイントのみ表示
+        let format = |c: u32| match char::from_u32(c) {
+            Some(ch) => format!("{}(U+{:04X})", ch, c),
+            None => format!("U+{:04X}", c),
+        };
         if self.start == self.end {
-            write!(
-                f,
-                "{}(U+{:04X})",
-                char::from_u32(self.start).unwrap(),
-                self.start
-            )
+            write!(f, "{}", format(self.start))
         } else {
-            write!(
-                f,
-                "{}(U+{:04X}) - {}(U+{:04X})",
-                char::from_u32(self.start).unwrap(),
-                self.start,
-                char::from_u32(self.end).unwrap(),
-                self.end
-            )
+            write!(f, "{} - {}", format(self.start), format(self.end))
         }
     }
 }
 
-#[derive(Debug)]
+// 範囲はstartの昇順に並び、重複・隣接しない
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct CharRangeList {
     ranges: Vec<CharRange>,
 }
@@ -237,8 +247,45 @@ impl CharRangeList {
         CharRangeList { ranges: Vec::new() }
     }
 
+    // startの昇順に並んだ範囲から作成し、重複・隣接する範囲をマージ
+    fn from_sorted(ranges: impl IntoIterator<Item = CharRange>) -> Self {
+        let mut list = CharRangeList::new();
+        for range in ranges {
+            list.push_merged(range);
+        }
+        list
+    }
+
+    // 末尾に範囲を追加 (rangeのstartは末尾の範囲のstart以上であること)
+    fn push_merged(&mut self, range: CharRange) {
+        match self.ranges.last_mut() {
+            Some(last) if last.merges_with(&range) => *last = last.merge(&range),
+            _ => self.ranges.push(range),
+        }
+    }
+
     pub fn contains(&self, c: u32) -> bool {
-        self.ranges.iter().any(|r| r.contains(c))
+        // cより後に始まる最初の範囲の1つ前を確認
+        let index = self.ranges.partition_point(|r| r.start <= c);
+        index > 0 && self.ranges[index - 1].contains(c)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn ranges(&self) -> &[CharRange] {
+        &self.ranges
+    }
+
+    // 範囲を順に返す
+    pub fn iter(&self) -> std::slice::Iter<'_, CharRange> {
+        self.ranges.iter()
+    }
+
+    // 含まれる文字のコードポイントを順に返す
+    pub fn code_points(&self) -> impl Iterator<Item = u32> + '_ {
+        self.ranges.iter().flat_map(|r| r.start..=r.end)
     }
 
     // 範囲を適切な位置に挿入し、前後を確認してマージ
@@ -294,9 +341,96 @@ impl CharRangeList {
 
     // 範囲の引き算
     pub fn subtract_range_list(&mut self, other: &CharRangeList) {
-        for range in other.ranges.iter() {
-            self.subtract_range(range);
+        *self = self.difference(other);
+    }
+
+    // 和集合
+    pub fn union(&self, other: &CharRangeList) -> CharRangeList {
+        let mut result = CharRangeList::new();
+        let (mut a, mut b) = (
+            self.ranges.iter().peekable(),
+            other.ranges.iter().peekable(),
+        );
+        loop {
+            // startの小さい方から順に追加
+            let next = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) if x.start <= y.start => a.next(),
+                (Some(_), Some(_)) => b.next(),
+                (Some(_), None) => a.next(),
+                (None, Some(_)) => b.next(),
+                (None, None) => break,
+            };
+            if let Some(range) = next {
+                result.push_merged(*range);
+            }
         }
+        result
+    }
+
+    // 積集合
+    pub fn intersection(&self, other: &CharRangeList) -> CharRangeList {
+        let mut result = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (x, y) = (self.ranges[i], other.ranges[j]);
+            let start = x.start.max(y.start);
+            let end = x.end.min(y.end);
+            if start <= end {
+                result.push(CharRange::new(start, end));
+            }
+            // 先に終わる方を進める
+            if x.end < y.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        CharRangeList::from_sorted(result)
+    }
+
+    // 差集合: self から other を引く
+    pub fn difference(&self, other: &CharRangeList) -> CharRangeList {
+        let mut result = Vec::new();
+        let mut j = 0;
+        for range in &self.ranges {
+            // rangeより前で終わる範囲を読み飛ばす
+            while j < other.ranges.len() && other.ranges[j].end < range.start {
+                j += 1;
+            }
+            let mut start = range.start;
+            let mut k = j;
+            let mut remaining = true;
+            while k < other.ranges.len() && other.ranges[k].start <= range.end {
+                let cut = other.ranges[k];
+                if cut.start > start {
+                    result.push(CharRange::new(start, cut.start - 1));
+                }
+                if cut.end >= range.end {
+                    remaining = false;
+                    break;
+                }
+                start = start.max(cut.end + 1);
+                k += 1;
+            }
+            if remaining {
+                result.push(CharRange::new(start, range.end));
+            }
+        }
+        CharRangeList::from_sorted(result)
+    }
+
+    // 対称差
+    pub fn symmetric_difference(&self, other: &CharRangeList) -> CharRangeList {
+        self.union(other).difference(&self.intersection(other))
+    }
+
+    // universe のうち self に含まれない文字
+    pub fn complement(&self, universe: CharRange) -> CharRangeList {
+        CharRangeList::from_sorted([universe]).difference(self)
+    }
+
+    pub fn is_subset(&self, other: &CharRangeList) -> bool {
+        self.difference(other).is_empty()
     }
 }
 
@@ -311,11 +445,27 @@ where
     T: Into<CharRange>,
 {
     fn from(ranges: Vec<T>) -> Self {
-        let mut list = CharRangeList::new();
-        for range in ranges {
-            list.add_range(range);
-        }
-        list
+        ranges.into_iter().collect()
+    }
+}
+
+impl<T> FromIterator<T> for CharRangeList
+where
+    T: Into<CharRange>,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut ranges: Vec<CharRange> = iter.into_iter().map(Into::into).collect();
+        ranges.sort_by_key(|r| r.start);
+        CharRangeList::from_sorted(ranges)
+    }
+}
+
+impl<T> Extend<T> for CharRangeList
+where
+    T: Into<CharRange>,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        *self = self.union(&iter.into_iter().collect());
     }
 }
 
@@ -328,6 +478,21 @@ impl IntoIterator for CharRangeList {
     }
 }
 
+impl<'a> IntoIterator for &'a CharRangeList {
+    type Item = &'a CharRange;
+    type IntoIter = std::slice::Iter<'a, CharRange>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.ranges.iter()
+    }
+}
+
+impl From<&CharRange> for CharRange {
+    fn from(range: &CharRange) -> Self {
+        *range
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -554,6 +719,114 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_display_surrogate() {
+        assert_eq!(CharRange::new_single(0x3042).to_string(), "あ(U+3042)");
+        assert_eq!(
+            CharRange::new(0xD800, 0xDFFF).to_string(),
+            "U+D800 - U+DFFF"
+        );
+    }
+
+    fn list(ranges: &[(u32, u32)]) -> CharRangeList {
+        ranges
+            .iter()
+            .map(|(start, end)| CharRange::new(*start, *end))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_iter_unsorted() {
+        let range_list = list(&[(0x60, 0x80), (0x20, 0x5A), (0x50, 0x5F)]);
+        assert_eq!(range_list.ranges(), &[CharRange::new(0x20, 0x80)]);
+    }
+
+    #[test]
+    fn test_contains() {
+        let range_list = list(&[(0x20, 0x5A), (0x60, 0x80)]);
+        assert!(range_list.contains(0x20));
+        assert!(range_list.contains(0x5A));
+        assert!(!range_list.contains(0x5B));
+        assert!(range_list.contains(0x80));
+        assert!(!range_list.contains(0x10));
+        assert!(!CharRangeList::new().contains(0));
+    }
+
+    #[test]
+    fn test_union() {
+        let a = list(&[(0x20, 0x30), (0x50, 0x60), (0x90, 0xA0)]);
+        let b = list(&[(0x31, 0x40), (0x55, 0x70)]);
+        assert_eq!(
+            a.union(&b),
+            list(&[(0x20, 0x40), (0x50, 0x70), (0x90, 0xA0)])
+        );
+    }
+
+    #[test]
+    fn test_intersection() {
+        let a = list(&[(0x20, 0x30), (0x50, 0x60), (0x90, 0xA0)]);
+        let b = list(&[(0x25, 0x55), (0x58, 0x95)]);
+        assert_eq!(
+            a.intersection(&b),
+            list(&[(0x25, 0x30), (0x50, 0x55), (0x58, 0x60), (0x90, 0x95)])
+        );
+        assert!(a.intersection(&CharRangeList::new()).is_empty());
+    }
+
+    #[test]
+    fn test_difference() {
+        let a = list(&[(0x20, 0x60), (0x90, 0xA0)]);
+        let b = list(&[(0x10, 0x25), (0x30, 0x31), (0x40, 0x95)]);
+        assert_eq!(
+            a.difference(&b),
+            list(&[(0x26, 0x2F), (0x32, 0x3F), (0x96, 0xA0)])
+        );
+        assert_eq!(a.difference(&CharRangeList::new()), a);
+        assert!(a.difference(&a).is_empty());
+
+        // 上限の値を含む場合にオーバーフローしない
+        let full = list(&[(0, u32::MAX)]);
+        assert_eq!(
+            full.difference(&list(&[(0x10, u32::MAX)])),
+            list(&[(0, 0x0F)])
+        );
+        assert_eq!(full.union(&list(&[(0x10, u32::MAX)])), full);
+    }
+
+    #[test]
+    fn test_symmetric_difference() {
+        let a = list(&[(0x20, 0x40)]);
+        let b = list(&[(0x30, 0x50)]);
+        assert_eq!(
+            a.symmetric_difference(&b),
+            list(&[(0x20, 0x2F), (0x41, 0x50)])
+        );
+    }
+
+    #[test]
+    fn test_complement_and_subset() {
+        let a = list(&[(0x20, 0x40), (0x50, 0x60)]);
+        assert_eq!(
+            a.complement(CharRange::new(0, 0xFFFF)),
+            list(&[(0, 0x1F), (0x41, 0x4F), (0x61, 0xFFFF)])
+        );
+        assert!(list(&[(0x25, 0x30)]).is_subset(&a));
+        assert!(!list(&[(0x25, 0x45)]).is_subset(&a));
+        assert!(CharRangeList::new().is_subset(&a));
+    }
+
+    #[test]
+    fn test_iterators() {
+        let mut a = list(&[(0x41, 0x43)]);
+        a.extend([CharRange::new_single(0x61)]);
+        assert_eq!(
+            a.code_points().collect::<Vec<_>>(),
+            vec![0x41, 0x42, 0x43, 0x61]
+        );
+        assert_eq!((&a).into_iter().count(), 2);
+        assert_eq!(a.iter().collect::<CharRangeList>(), a);
+    }
+
     // テストケース1: 完全一致
     #[test]
     fn test_subtract_full_range() {
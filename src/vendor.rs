@@ -0,0 +1,258 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{BufWriter, Write as _},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use font_kit::{
+    source::{Source, SystemSource},
+    sources::{fs::FsSource, multi::MultiSource},
+};
+
+use crate::{types::FontNameBundle, xml::DynamicFontBuilderBundle};
+
+pub const VENDOR_DIR: &str = "vendor";
+
+// フォントと一緒に配布されているライセンスファイルの名前 (小文字で比較)
+const LICENSE_FILE_PREFIXES: &[&str] = &["license", "licence", "ofl", "copying"];
+
+// fontforgeで生成したフォントのライセンス置き場
+const FONTFORGE_TTF_DIR: &str = "fontforge/ttf";
+
+// フォントを検索するSourceを作成
+// vendorディレクトリにフォントがあればそちらを優先し、無ければシステムフォントを使用する
+pub fn font_source() -> MultiSource {
+    let mut sources: Vec<Box<dyn Source>> = Vec::new();
+    if Path::new(VENDOR_DIR).is_dir() {
+        sources.push(Box::new(FsSource::in_path(VENDOR_DIR)));
+    }
+    sources.push(Box::new(SystemSource::new()));
+    MultiSource::from_sources(sources)
+}
+
+#[derive(Debug)]
+pub struct VendoredFont {
+    pub full_name: String,
+    pub source: PathBuf,
+    pub path: PathBuf,
+    pub licenses: Vec<PathBuf>,
+}
+
+// 全てのバンドルで使われているフォントをvendor_dirにコピー
+pub fn vendor_fonts(
+    bundles: &[DynamicFontBuilderBundle],
+    vendor_dir: &Path,
+) -> anyhow::Result<Vec<VendoredFont>> {
+    let font_source = font_source();
+    let vendored = used_fonts(bundles, &font_source)?
+        .iter()
+        .map(|font_name| vendor_font(font_name, &font_source, vendor_dir))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    write_manifest(&vendored, vendor_dir)?;
+    Ok(vendored)
+}
+
+// ビルド時にvendor_dirではなくシステムフォントから読み込まれるフォント (フルネームとパス)
+// vendorのコピーが読み込めない場合等は、font_source()がシステムフォントにフォールバックする
+pub fn system_resolved_fonts(
+    bundles: &[DynamicFontBuilderBundle],
+    vendor_dir: &Path,
+) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    let font_source = font_source();
+    let vendor_dir = vendor_dir.canonicalize()?;
+    let mut system_fonts = Vec::new();
+    for font_name in used_fonts(bundles, &font_source)? {
+        let path = font_name.path(&font_source)?;
+        if !path
+            .canonicalize()
+            .is_ok_and(|path| path.starts_with(&vendor_dir))
+        {
+            system_fonts.push((font_name.full.to_string(), path));
+        }
+    }
+    Ok(system_fonts)
+}
+
+// 全てのバンドルで使われているフォント (重複は除く)
+fn used_fonts(
+    bundles: &[DynamicFontBuilderBundle],
+    font_source: &dyn Source,
+) -> anyhow::Result<Vec<FontNameBundle<'static>>> {
+    let mut visited = HashSet::new();
+    let mut fonts = Vec::new();
+    for builder in bundles.iter().flat_map(|bundle| bundle.fonts.iter()) {
+        for font_name in builder.font_names() {
+            let font_name_bundle = font_name
+                .to_bundle(font_source)
+                .with_context(|| format!("Failed to resolve font: {}", font_name))?;
+            if visited.insert(font_name_bundle.full.to_string()) {
+                fonts.push(font_name_bundle);
+            }
+        }
+    }
+    Ok(fonts)
+}
+
+fn vendor_font(
+    font_name: &FontNameBundle,
+    source: &dyn Source,
+    vendor_dir: &Path,
+) -> anyhow::Result<VendoredFont> {
+    let source_path = font_name.path(source)?;
+    let file_name = source_path
+        .file_name()
+        .with_context(|| format!("Invalid font path: {}", source_path.display()))?;
+    let target_dir = vendor_dir.join(sanitize_file_name(&font_name.family));
+    let target_path = target_dir.join(file_name);
+
+    fs::create_dir_all(&target_dir)?;
+
+    // 既にvendorされている場合はコピーしない
+    if !same_file(&source_path, &target_path) {
+        println!("{} -> {}", source_path.display(), target_path.display());
+        fs::copy(&source_path, &target_path)?;
+    }
+
+    let mut licenses = Vec::new();
+    for license_path in find_license_files(&source_path, Path::new(FONTFORGE_TTF_DIR))? {
+        let target_license = target_dir.join(license_path.file_name().context("No file name")?);
+        if !same_file(&license_path, &target_license) {
+            fs::copy(&license_path, &target_license)?;
+        }
+        licenses.push(target_license);
+    }
+    if licenses.is_empty() {
+        println!("warning: No license file found for {}", font_name.full);
+    }
+
+    Ok(VendoredFont {
+        full_name: font_name.full.to_string(),
+        source: source_path,
+        path: target_path,
+        licenses,
+    })
+}
+
+// フォントファイルと同じディレクトリにあるライセンスファイルを探す
+fn find_license_files(font_path: &Path, fontforge_ttf_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut licenses = Vec::new();
+
+    if let Some(dir) = font_path.parent() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            if path.is_file()
+                && LICENSE_FILE_PREFIXES
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+            {
+                licenses.push(path);
+            }
+        }
+    }
+
+    // fontforgeで生成したフォントは`fontforge/ttf/LICENSE.txt`を使用
+    if licenses.is_empty() {
+        if let Some(file_name) = font_path.file_name() {
+            let license = fontforge_ttf_dir.join("LICENSE.txt");
+            if fontforge_ttf_dir.join(file_name).is_file() && license.is_file() {
+                licenses.push(license);
+            }
+        }
+    }
+
+    licenses.sort();
+    Ok(licenses)
+}
+
+// vendorしたフォントの一覧を書き出し
+fn write_manifest(vendored: &[VendoredFont], vendor_dir: &Path) -> anyhow::Result<()> {
+    let file = File::create(vendor_dir.join("vendor.yml"))?;
+    let mut writer = BufWriter::new(file);
+
+    for font in vendored {
+        writeln!(writer, "- full_name: \"{}\"", font.full_name)?;
+        writeln!(writer, "  source: \"{}\"", font.source.display())?;
+        writeln!(writer, "  path: \"{}\"", font.path.display())?;
+        writeln!(writer, "  licenses:")?;
+        for license in &font.licenses {
+            writeln!(writer, "    - \"{}\"", license.display())?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_license_files() {
+        let dir = std::env::temp_dir().join(format!("font-vendor-{}", std::process::id()));
+        let font_dir = dir.join("fonts");
+        let ttf_dir = dir.join("fontforge").join("ttf");
+        fs::create_dir_all(&font_dir).unwrap();
+        fs::create_dir_all(&ttf_dir).unwrap();
+        for name in ["Test.ttf", "OFL.txt", "LICENSE", "Copying.md", "README.md"] {
+            fs::write(font_dir.join(name), "").unwrap();
+        }
+
+        // 名前の先頭が一致するファイル (大文字小文字は区別しない)
+        let licenses = find_license_files(&font_dir.join("Test.ttf"), &ttf_dir).unwrap();
+        assert_eq!(
+            licenses,
+            vec![
+                font_dir.join("Copying.md"),
+                font_dir.join("LICENSE"),
+                font_dir.join("OFL.txt"),
+            ]
+        );
+
+        // ライセンスが無いフォントは、fontforge/ttfに同名のフォントがあればそのライセンスを使用
+        fs::write(dir.join("Generated.ttf"), "").unwrap();
+        assert!(find_license_files(&dir.join("Generated.ttf"), &ttf_dir)
+            .unwrap()
+            .is_empty());
+        fs::write(ttf_dir.join("Generated.ttf"), "").unwrap();
+        fs::write(ttf_dir.join("LICENSE.txt"), "").unwrap();
+        assert_eq!(
+            find_license_files(&dir.join("Generated.ttf"), &ttf_dir).unwrap(),
+            vec![ttf_dir.join("LICENSE.txt")]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("Noto Sans JP"), "Noto Sans JP");
+        assert_eq!(
+            sanitize_file_name("A/B\\C:D*E?F\"G<H>I|J"),
+            "A_B_C_D_E_F_G_H_I_J"
+        );
+    }
+}
@@ -6,7 +6,7 @@ use std::{
 const DYNAMIC_FONT_GENERATOR_EXE: &str = r"dfg/DynamicFontGenerator.exe";
 
 pub fn generate_dynamic_font(target_dir: &Path) -> anyhow::Result<()> {
-    if !Path::new(DYNAMIC_FONT_GENERATOR_EXE).is_file(){
+    if !Path::new(DYNAMIC_FONT_GENERATOR_EXE).is_file() {
         anyhow::bail!(
             "The DynamicFontGenerator executable could not be found at the specified path: {}\n\
             To proceed, please download the required executable from the link below and ensure it \
@@ -15,7 +15,7 @@ pub fn generate_dynamic_font(target_dir: &Path) -> anyhow::Result<()> {
             DYNAMIC_FONT_GENERATOR_EXE
         );
     }
-    
+
     let output = Command::new(DYNAMIC_FONT_GENERATOR_EXE)
         .current_dir(target_dir)
         .stdout(Stdio::inherit())
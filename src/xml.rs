@@ -1,16 +1,37 @@
 use std::{
     borrow::Cow,
     fs,
+    io::{self, Write},
     path::{Path, PathBuf},
 };
 
 use anyhow::Context;
-use font_kit::source::SystemSource;
-use serde::{Serialize, Serializer};
+use quick_xml::{
+    events::{BytesDecl, BytesText, Event},
+    Writer,
+};
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::types::{CharRange, CharRangeList, FontName, FontNameBundle};
+use crate::{
+    bitmap::BitmapFont,
+    budget::{BudgetReport, GlyphBudget},
+    charset::PresetCoverage,
+    corpus::{Corpus, CorpusReport},
+    explain::FontAttribution,
+    filter::{CharFilter, FontFilter},
+    font_info::FontInfo,
+    glyph::{find_invalid_glyphs, find_unsupported_code_points, CodePointPolicy, ExcludedGlyphs},
+    license::FontLicense,
+    metrics::{MatchedSize, SizeMatch, VerticalAlignment, VerticalMetrics},
+    output::FontOutput,
+    region::RegionMerge,
+    report::{BuildReport, FontReport},
+    required::{check_required_chars, RequiredChars},
+    types::{CharRange, CharRangeList, Counter, FontName, FontNameBundle},
+    vendor::font_source,
+};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct XnaContent {
     #[serde(skip)]
@@ -21,20 +42,44 @@ pub struct XnaContent {
 }
 
 impl XnaContent {
-    fn to_xml(&self) -> String {
-        let mut buffer = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    fn to_xml(&self) -> anyhow::Result<String> {
+        let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
+        writer
+            .create_element("XnaContent")
+            .with_attribute(("xmlns:Graphics", self.pipeline.as_str()))
+            .write_inner_content(|writer| {
+                writer
+                    .create_element("Asset")
+                    .with_attribute(("Type", self.asset.r#type.as_str()))
+                    .write_inner_content(|writer| self.asset.font.write_xml(writer))?;
+                Ok(())
+            })?;
 
-        let mut ser = quick_xml::se::Serializer::new(&mut buffer);
-        ser.indent(' ', 2);
-        self.serialize(ser).unwrap();
+        Ok(String::from_utf8(writer.into_inner())?)
+    }
 
-        buffer = buffer.replace("&amp;", "&");
+    // .dynamicfontファイルを読み込む
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut xna_content = Self::from_xml(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        xna_content.file_name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        xna_content.asset.font.file_name = xna_content.file_name.clone();
+        Ok(xna_content)
+    }
 
-        buffer
+    pub fn from_xml(content: &str) -> anyhow::Result<Self> {
+        let content = escape_whitespace_chars(content)?;
+        Ok(quick_xml::de::from_str(&content)?)
     }
 
     fn write(&self, directory: &Path) -> anyhow::Result<PathBuf> {
-        let content = self.to_xml();
+        let content = self.to_xml()?;
         fs::create_dir_all(directory)?;
         let path = directory
             .join(&self.file_name)
@@ -44,18 +89,77 @@ impl XnaContent {
     }
 }
 
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Debug, Deserialize)]
+#[serde(from = "XnaAssetFields")]
 pub struct XnaAsset {
-    #[serde(rename = "@Type")]
     r#type: String,
-    #[serde(flatten)]
     pub font: DynamicFont,
 }
 
+// quick-xmlのflattenは数値等を読み込めないため、フィールドを展開して読み込む
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct XnaAssetFields {
+    #[serde(rename = "@Type")]
+    r#type: String,
+    font_name: String,
+    size: f32,
+    #[serde(default)]
+    spacing: f32,
+    #[serde(default)]
+    use_kerning: bool,
+    #[serde(default = "default_font_style")]
+    style: FontStyle,
+    #[serde(
+        default = "default_default_character",
+        deserialize_with = "deserialize_char_from_xml"
+    )]
+    default_character: char,
+    #[serde(default = "default_vertical_offset")]
+    vertical_offset: VerticalOffset,
+    #[serde(default)]
+    character_regions: CharacterRegions,
+}
+
+fn default_default_character() -> char {
+    '*'
+}
+
+fn default_font_style() -> FontStyle {
+    FontStyle::Regular
+}
+
+fn default_vertical_offset() -> VerticalOffset {
+    VerticalOffset::DefaultFontAscent
+}
+
+impl From<XnaAssetFields> for XnaAsset {
+    fn from(fields: XnaAssetFields) -> Self {
+        Self {
+            r#type: fields.r#type,
+            font: DynamicFont {
+                file_name: String::new(),
+                font_name: fields.font_name,
+                size: fields.size,
+                spacing: fields.spacing,
+                use_kerning: fields.use_kerning,
+                style: fields.style,
+                default_character: fields.default_character,
+                vertical_offset: fields.vertical_offset,
+                character_regions: fields.character_regions,
+                bitmap_overrides: Vec::new(),
+            },
+        }
+    }
+}
+
 pub struct DynamicFontBuilderBundle {
     pub directory: PathBuf,
     pub fonts: Vec<DynamicFontBuilder>,
+    // バンドル内の全てのフォントに必須の文字
+    pub required_chars: Vec<RequiredChars>,
+    // .dynamicfontに加えて書き出す形式
+    pub outputs: Vec<FontOutput>,
 }
 
 impl DynamicFontBuilderBundle {
@@ -63,6 +167,8 @@ impl DynamicFontBuilderBundle {
         Self {
             directory: directory.into(),
             fonts: Vec::new(),
+            required_chars: Vec::new(),
+            outputs: Vec::new(),
         }
     }
 
@@ -70,44 +176,181 @@ impl DynamicFontBuilderBundle {
         self.fonts.push(font);
     }
 
+    // バンドル内の全てのフォントに必須の文字を追加
+    #[allow(unused)]
+    pub fn require(&mut self, required: impl Into<RequiredChars>) {
+        self.required_chars.push(required.into());
+    }
+
+    // .spritefont等の書き出す形式を追加
+    #[allow(unused)]
+    pub fn output(&mut self, output: FontOutput) {
+        self.outputs.push(output);
+    }
+
     pub fn build(self) -> anyhow::Result<Vec<PathBuf>> {
-        let fonts = self
-            .fonts
-            .into_iter()
-            .map(|builder| builder.build()?.pack().write(&self.directory))
-            .collect::<anyhow::Result<Vec<PathBuf>>>()?;
+        let mut report = BuildReport::default();
+        let mut fonts = Vec::with_capacity(self.fonts.len());
+        for builder in self.fonts {
+            let builder = self
+                .required_chars
+                .iter()
+                .fold(builder, |builder, required| {
+                    builder.require(required.clone())
+                });
+            let (font, mut font_report) = builder.build()?;
+            for output in &self.outputs {
+                let (_, warnings) = output.write(&font, &self.directory)?;
+                font_report.output_warnings.extend(warnings);
+            }
+            fonts.push(font.pack().write(&self.directory)?);
+            report.fonts.push(font_report);
+        }
+
+        report.print_summary();
+        let report_path = report.write(&self.directory)?;
+        println!("Report: {}\n", report_path.display());
+
         Ok(fonts)
     }
 }
 
+// フォールバックのフォントの読み込み元
+#[derive(Clone)]
+enum FallbackSource {
+    Font(FontName<'static>),
+    // BMFontの.fnt (SpriteFontXnb、BmFont等のラスタライズする出力のみで使用)
+    Bitmap(PathBuf),
+}
+
+// DynamicFontBuilderに追加するフォントとその設定
+#[derive(Clone)]
+pub struct FallbackFont {
+    source: FallbackSource,
+    filter: FontFilter,
+    size: Option<FallbackSize>,
+    style: Option<FontStyle>,
+}
+
+impl FallbackFont {
+    pub fn new(font_name: FontName<'static>) -> Self {
+        Self {
+            source: FallbackSource::Font(font_name),
+            filter: FontFilter::default(),
+            size: None,
+            style: None,
+        }
+    }
+
+    // 既存のビットマップフォント (.fntとPNGのページ) のグリフをそのまま使用
+    // サイズとスタイルは無視され、.dynamicfontには書き出されない
+    #[allow(unused)]
+    pub fn bitmap(path: impl Into<PathBuf>) -> Self {
+        Self {
+            source: FallbackSource::Bitmap(path.into()),
+            filter: FontFilter::default(),
+            size: None,
+            style: None,
+        }
+    }
+
+    // このフォントの文字のサイズ
+    #[allow(unused)]
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = Some(FallbackSize::Absolute(size));
+        self
+    }
+
+    // このフォントの文字のサイズ (DynamicFontBuilderのサイズに対する倍率)
+    #[allow(unused)]
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.size = Some(FallbackSize::Scale(scale));
+        self
+    }
+
+    // このフォントの文字のスタイル
+    #[allow(unused)]
+    pub fn style(mut self, style: FontStyle) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    // 指定したフィルターのいずれかに一致する文字のみを使用
+    #[allow(unused)]
+    pub fn include(mut self, filter: impl Into<CharFilter>) -> Self {
+        self.filter.include.push(filter.into());
+        self
+    }
+
+    // 指定したフィルターに一致する文字を使用しない
+    #[allow(unused)]
+    pub fn exclude(mut self, filter: impl Into<CharFilter>) -> Self {
+        self.filter.exclude.push(filter.into());
+        self
+    }
+
+    // 指定したフィルターに一致する文字は、前のフォントに含まれていてもこのフォントから使用
+    #[allow(unused)]
+    pub fn prefer(mut self, filter: impl Into<CharFilter>) -> Self {
+        self.filter.prefer.push(filter.into());
+        self
+    }
+}
+
+impl From<FontName<'static>> for FallbackFont {
+    fn from(font_name: FontName<'static>) -> Self {
+        Self::new(font_name)
+    }
+}
+
 #[derive(Clone)]
 pub struct DynamicFontBuilder {
     file_name: String,
-    font_name_list: Vec<FontName<'static>>,
+    font_list: Vec<FallbackFont>,
     size: f32,
     spacing: f32,
     use_kerning: bool,
     style: FontStyle,
-    default_character: char,
+    default_characters: Vec<char>,
     vertical_offset: VerticalOffset,
+    allow_restricted_license: bool,
+    corpus: Option<Corpus>,
+    budget: Option<GlyphBudget>,
+    size_match: Option<SizeMatch>,
+    drop_invalid_glyphs: bool,
+    code_point_policy: CodePointPolicy,
+    required_chars: Vec<RequiredChars>,
+    region_merge: Option<RegionMerge>,
 }
 
 impl DynamicFontBuilder {
     pub fn new() -> Self {
         Self {
             file_name: String::new(),
-            font_name_list: Vec::new(),
+            font_list: Vec::new(),
             size: 16.0,
             spacing: 0.0,
             use_kerning: true,
             style: FontStyle::Regular,
-            default_character: '*',
+            default_characters: vec!['*'],
             vertical_offset: VerticalOffset::DefaultFontAscent,
+            allow_restricted_license: false,
+            corpus: None,
+            budget: None,
+            size_match: None,
+            drop_invalid_glyphs: false,
+            code_point_policy: CodePointPolicy::Drop,
+            required_chars: Vec::new(),
+            region_merge: None,
         }
     }
 
-    pub fn add_font_name<'a>(mut self, font_name: FontName<'static>) -> Self {
-        self.font_name_list.push(font_name);
+    pub fn add_font_name(self, font_name: FontName<'static>) -> Self {
+        self.add_font(FallbackFont::new(font_name))
+    }
+
+    pub fn add_font(mut self, font: impl Into<FallbackFont>) -> Self {
+        self.font_list.push(font.into());
         self
     }
 
@@ -143,7 +386,14 @@ impl DynamicFontBuilder {
 
     #[allow(unused)]
     pub fn default_character(mut self, default_character: char) -> Self {
-        self.default_character = default_character;
+        self.default_characters = vec![default_character];
+        self
+    }
+
+    // いずれかのフォントに含まれる最初の文字をdefault_characterとして使用
+    #[allow(unused)]
+    pub fn default_characters<T: IntoIterator<Item = char>>(mut self, candidates: T) -> Self {
+        self.default_characters = candidates.into_iter().collect();
         self
     }
 
@@ -153,88 +403,609 @@ impl DynamicFontBuilder {
         self
     }
 
-    pub fn build(self) -> anyhow::Result<DynamicFont> {
-        if self.font_name_list.is_empty() {
+    // fsTypeがRestricted License embeddingのフォントの使用を許可
+    #[allow(unused)]
+    pub fn allow_restricted_license(mut self, allow_restricted_license: bool) -> Self {
+        self.allow_restricted_license = allow_restricted_license;
+        self
+    }
+
+    // ローカライズファイルで使用されている文字のみを含める
+    #[allow(unused)]
+    pub fn corpus(mut self, corpus: Corpus) -> Self {
+        self.corpus = Some(corpus);
+        self
+    }
+
+    // 文字数の上限
+    #[allow(unused)]
+    pub fn budget(mut self, budget: GlyphBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    // フォールバックのサイズを基準のフォントのx-height等に合わせる
+    #[allow(unused)]
+    pub fn match_size(mut self, size_match: SizeMatch) -> Self {
+        self.size_match = Some(size_match);
+        self
+    }
+
+    // cmapに含まれていても、.notdefや空のグリフに割り当てられた文字を使用しない
+    // 空白文字は除外しない
+    #[allow(unused)]
+    pub fn drop_invalid_glyphs(mut self, drop_invalid_glyphs: bool) -> Self {
+        self.drop_invalid_glyphs = drop_invalid_glyphs;
+        self
+    }
+
+    // U+FFFFより大きい文字とサロゲートを除外するか、エラーにするか
+    #[allow(unused)]
+    pub fn code_point_policy(mut self, code_point_policy: CodePointPolicy) -> Self {
+        self.code_point_policy = code_point_policy;
+        self
+    }
+
+    // ビルド結果に必ず含まれていなければならない文字 (含まれていなければ例外)
+    #[allow(unused)]
+    pub fn require(mut self, required: impl Into<RequiredChars>) -> Self {
+        self.required_chars.push(required.into());
+        self
+    }
+
+    // 小さな隙間を埋めてCharacterRegionの数を減らす
+    #[allow(unused)]
+    pub fn merge_regions(mut self, region_merge: RegionMerge) -> Self {
+        self.region_merge = Some(region_merge);
+        self
+    }
+
+    // 書き出すファイル名 (拡張子を除く)
+    pub fn output_name(&self) -> &str {
+        &self.file_name
+    }
+
+    // ビットマップフォントを除くフォント名
+    pub fn font_names(&self) -> impl Iterator<Item = &FontName<'static>> {
+        self.font_list.iter().filter_map(|font| match &font.source {
+            FallbackSource::Font(font_name) => Some(font_name),
+            FallbackSource::Bitmap(_) => None,
+        })
+    }
+
+    pub fn build(self) -> anyhow::Result<(DynamicFont, FontReport)> {
+        if self.font_list.is_empty() {
             anyhow::bail!("No font names specified");
         }
 
-        let font_system_source = SystemSource::new();
+        let font_system_source = font_source();
 
-        // 全てのFontNameをFullNameに変換
-        let font_name_bundle_list = self
-            .font_name_list
+        // 全てのFontNameをFullNameに変換し、ビットマップフォントを読み込む (名前はface)
+        let (bitmap_fonts, font_name_bundle_list): (Vec<Option<BitmapFont>>, Vec<FontNameBundle>) =
+            self.font_list
+                .iter()
+                .map(|font| -> anyhow::Result<_> {
+                    match &font.source {
+                        FallbackSource::Font(name) => {
+                            Ok((None, name.to_bundle(&font_system_source)?))
+                        }
+                        FallbackSource::Bitmap(path) => {
+                            let bitmap = BitmapFont::load(path)?;
+                            let name = bitmap.name_bundle();
+                            Ok((Some(bitmap), name))
+                        }
+                    }
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .into_iter()
+                .unzip();
+        if matches!(bitmap_fonts.first(), Some(Some(_))) {
+            anyhow::bail!(
+                "{}: The first font must not be a bitmap font",
+                self.file_name
+            );
+        }
+        // faceが同じビットマップフォントはレポートで区別できない
+        let faces: Vec<&str> = bitmap_fonts
+            .iter()
+            .flatten()
+            .map(|bitmap| bitmap.face.as_str())
+            .collect();
+        if let Some(face) = faces
             .iter()
-            .map(|name| -> anyhow::Result<_> { name.into_bundle(&font_system_source) })
-            .collect::<anyhow::Result<Vec<FontNameBundle>>>()?;
+            .enumerate()
+            .find_map(|(index, face)| faces[..index].contains(face).then_some(face))
+        {
+            anyhow::bail!(
+                "{}: Multiple bitmap fonts have the same face: {}",
+                self.file_name,
+                face
+            );
+        }
 
-        // フォントを読み込み、サポートされている文字を取得
-        let mut include_chars: Vec<CharRangeList> = Vec::with_capacity(font_name_bundle_list.len());
-        for font_name in font_name_bundle_list.iter() {
-            let font_path = font_name.path(&font_system_source)?;
-            let font_file = font::File::open(font_path)?;
+        // フォントの埋め込み許可とライセンスを確認 (ビットマップフォントは対象外)
+        let licenses = font_name_bundle_list
+            .iter()
+            .zip(bitmap_fonts.iter())
+            .filter(|(_, bitmap)| bitmap.is_none())
+            .map(|(name, _)| FontLicense::load(name, &font_system_source))
+            .collect::<anyhow::Result<Vec<FontLicense>>>()?;
+        let restricted: Vec<_> = licenses.iter().filter(|l| l.is_restricted()).collect();
+        if !restricted.is_empty() && !self.allow_restricted_license {
+            let list = restricted
+                .iter()
+                .map(|l| format!("  {}", l))
+                .collect::<Vec<_>>()
+                .join("\n");
+            anyhow::bail!(
+                "{}: Restricted-license fonts cannot be embedded:\n{}\n\
+                Use `allow_restricted_license(true)` only if you are permitted to redistribute them.",
+                self.file_name,
+                list
+            );
+        }
 
-            let mut supported_chars = Vec::new();
+        // 必須の文字を読み込む
+        let required_chars = self
+            .required_chars
+            .iter()
+            .map(|required| Ok((required.clone(), required.chars()?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
 
-            for mut f in font_file.fonts {
-                supported_chars.extend(f.characters()?);
+        // コーパスが指定されていれば、使用されている文字、default_characterの候補、必須の文字のみを含める
+        let corpus_chars = self.corpus.as_ref().map(Corpus::scan).transpose()?;
+        let allowed_chars = corpus_chars.as_ref().map(|corpus_chars| {
+            let mut chars = corpus_chars.chars();
+            for c in &self.default_characters {
+                chars.add_range(CharRange::new_single(*c as u32));
+            }
+            for (_, required) in &required_chars {
+                chars = chars.union(required);
             }
+            chars
+        });
 
-            let mut supported_chars = CharRangeList::from(supported_chars);
+        // メトリクスの確認用にフォントを読み込む
+        let loaded_fonts = font_name_bundle_list
+            .iter()
+            .zip(bitmap_fonts.iter())
+            .map(|(name, bitmap)| -> anyhow::Result<_> {
+                if bitmap.is_some() {
+                    return Ok(None);
+                }
+                let font = name.get_font_handle(&font_system_source)?.load()?;
+                let info = FontInfo::from_font(&font);
+                Ok(Some((font, info)))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
 
-            for chars in include_chars.iter() {
-                supported_chars.subtract_range_list(&chars);
+        // フォント毎のサイズ (明示的に指定されたサイズを優先、ビットマップフォントは無し)
+        let mut region_sizes: Vec<Option<f32>> = self
+            .font_list
+            .iter()
+            .zip(bitmap_fonts.iter())
+            .map(|(font, bitmap)| match bitmap {
+                Some(_) => None,
+                None => font.size.map(|size| size.resolve(self.size)),
+            })
+            .collect();
+        let mut matched_sizes = Vec::new();
+        if let Some(size_match) = self.size_match {
+            let metrics = loaded_fonts
+                .iter()
+                .map(|loaded| {
+                    loaded
+                        .as_ref()
+                        .and_then(|(font, info)| size_match.measure(font, info.os2.as_ref()))
+                })
+                .collect::<Vec<_>>();
+
+            match metrics.first().copied().flatten() {
+                Some(base_metric) => {
+                    for (index, (font_name, metric)) in
+                        font_name_bundle_list.iter().zip(metrics).enumerate()
+                    {
+                        if bitmap_fonts[index].is_some() {
+                            continue;
+                        }
+                        let Some(metric) = metric else {
+                            println!(
+                                "Warning: {}: Could not measure {} of {}",
+                                self.file_name, size_match, font_name.full
+                            );
+                            continue;
+                        };
+                        let size = if index == 0 {
+                            self.size
+                        } else {
+                            metric.matched_size(&base_metric, self.size)
+                        };
+                        if index > 0 && region_sizes[index].is_none() {
+                            region_sizes[index] = Some(size);
+                        }
+                        matched_sizes.push(MatchedSize {
+                            font_name: font_name.full.to_string(),
+                            size_match,
+                            metric: (metric.value * 1000.0).round() / 1000.0,
+                            source: metric.source,
+                            size: region_sizes[index].unwrap_or(size),
+                        });
+                    }
+                }
+                None => println!(
+                    "Warning: {}: Could not measure {} of the primary font, sizes are not matched",
+                    self.file_name, size_match
+                ),
             }
+        }
+
+        // 縦方向のメトリクスを比較
+        let vertical = VerticalAlignment::compare(
+            font_name_bundle_list
+                .iter()
+                .zip(loaded_fonts.iter())
+                .zip(region_sizes.iter())
+                .filter_map(|((name, loaded), size)| {
+                    let (font, info) = loaded.as_ref()?;
+                    Some(VerticalMetrics::from_font(
+                        name.full.to_string(),
+                        font,
+                        info,
+                        size.unwrap_or(self.size),
+                    ))
+                })
+                .collect(),
+            self.vertical_offset,
+        );
+
+        // フォントを読み込み、サポートされている文字を取得
+        let mut font_chars: Vec<CharRangeList> = Vec::with_capacity(font_name_bundle_list.len());
+        let mut cmap_chars = Vec::with_capacity(font_name_bundle_list.len());
+        let mut excluded_glyphs = Vec::new();
+        for (((font_name, font), loaded), bitmap) in font_name_bundle_list
+            .iter()
+            .zip(self.font_list.iter())
+            .zip(loaded_fonts.iter())
+            .zip(bitmap_fonts.iter())
+        {
+            let mut supported_chars = match bitmap {
+                Some(bitmap) => bitmap.characters(),
+                None => font_name.characters(&font_system_source)?,
+            };
+            cmap_chars.push(supported_chars.clone());
+
+            // 前のフォントの文字を除外する前にフィルターを適用
+            if !font.filter.is_empty() {
+                supported_chars = font.filter.apply(&supported_chars)?;
+            }
+            if let Some(allowed_chars) = &allowed_chars {
+                supported_chars = supported_chars.intersection(allowed_chars);
+            }
+
             // null文字等を除外
-            supported_chars.subtract_range(&CharRange::new(0, 31));
+            supported_chars.subtract_range(CharRange::new(0, 31));
+
+            // XNAのcharで表せない文字
+            for (reason, chars) in find_unsupported_code_points(&supported_chars) {
+                if self.code_point_policy == CodePointPolicy::Error {
+                    anyhow::bail!(
+                        "{}: {} contains {} characters:\n  {}",
+                        self.file_name,
+                        font_name.full,
+                        reason,
+                        chars
+                            .ranges()
+                            .iter()
+                            .map(|r| r.to_string())
+                            .collect::<Vec<_>>()
+                            .join("\n  ")
+                    );
+                }
+                supported_chars.subtract_range_list(&chars);
+                excluded_glyphs.push(ExcludedGlyphs {
+                    font_name: font_name.full.to_string(),
+                    reason,
+                    ranges: chars.ranges().iter().map(|r| r.to_string()).collect(),
+                });
+            }
 
-            include_chars.push(supported_chars);
+            // .notdefや空のグリフに割り当てられた文字は後のフォントから使用
+            if let (true, Some((loaded_font, _))) = (self.drop_invalid_glyphs, loaded) {
+                for (reason, chars) in find_invalid_glyphs(loaded_font, &supported_chars) {
+                    supported_chars.subtract_range_list(&chars);
+                    excluded_glyphs.push(ExcludedGlyphs {
+                        font_name: font_name.full.to_string(),
+                        reason,
+                        ranges: chars.ranges().iter().map(|r| r.to_string()).collect(),
+                    });
+                }
+            }
+
+            font_chars.push(supported_chars);
         }
 
-        // ビルド結果にdefault_characterが含まれない場合に例外
-        if include_chars
+        // preferで指定された文字を取得
+        let mut preferred_chars = Vec::with_capacity(font_chars.len());
+        for ((font_name, font), chars) in font_name_bundle_list
             .iter()
-            .all(|chars| !chars.contains(self.default_character as u32))
+            .zip(self.font_list.iter())
+            .zip(font_chars.iter())
         {
-            anyhow::bail!("Default character not found in any font.\nYou must include the default character in at least one font.");
+            let preferred = font.filter.preferred(chars)?;
+            if !font.filter.prefer.is_empty() && preferred.count() == 0 {
+                println!(
+                    "Warning: {}: No preferred characters found in {}",
+                    self.file_name, font_name.full
+                );
+            }
+            preferred_chars.push(preferred);
         }
 
+        // ReLogicのパイプラインはビットマップフォントを読み込めないため、.dynamicfontの文字はベクターフォントのみに割り当てる
+        let is_bitmap: Vec<bool> = bitmap_fonts.iter().map(Option::is_some).collect();
+        let (mut include_chars, bitmap_chars) =
+            assign_chars_with_bitmaps(font_chars.clone(), preferred_chars, &is_bitmap);
+
+        // ビルド結果にdefault_characterが含まれない場合に例外
+        let default_character = select_default_character(
+            &self.default_characters,
+            &font_name_bundle_list,
+            &include_chars,
+        )?;
+
+        // 上限を超えた文字を優先順位の低いものから除外
+        let budget = match &self.budget {
+            Some(budget) => {
+                let all_chars = include_chars
+                    .iter()
+                    .fold(CharRangeList::new(), |all, chars| all.union(chars));
+                // 必須の文字は上限を超えても残す
+                let must_keep = required_chars
+                    .iter()
+                    .fold(CharRangeList::new(), |all, (_, chars)| all.union(chars));
+                let dropped = budget.apply(&all_chars, default_character, &must_keep)?;
+                for chars in include_chars.iter_mut() {
+                    chars.subtract_range_list(&dropped);
+                }
+                Some(BudgetReport {
+                    max_chars: budget.max_chars(),
+                    dropped_count: dropped.count(),
+                    dropped,
+                })
+            }
+            None => None,
+        };
+
+        // プリセットの文字集合をどれだけ含んでいるか
+        let all_chars = include_chars
+            .iter()
+            .fold(CharRangeList::new(), |all, chars| all.union(chars));
+
+        // 必須の文字が含まれていなければ例外
+        check_required_chars(&required_chars, &all_chars)
+            .with_context(|| format!("{}: Build failed", self.file_name))?;
+
         let base_font = font_name_bundle_list
             .first()
             .context("At least one font must be specified")?;
 
+        // どのフォントがどの文字を提供するか
+        let attribution = font_name_bundle_list
+            .iter()
+            .zip(cmap_chars.iter().cloned())
+            .zip(font_chars)
+            .zip(include_chars.iter())
+            .map(
+                |(((font_name, cmap), candidates), assigned)| FontAttribution {
+                    font_name: font_name.full.to_string(),
+                    cmap,
+                    candidates,
+                    assigned: assigned.clone(),
+                },
+            )
+            .collect();
+
+        // ビットマップフォントはラスタライズする出力で.dynamicfontの文字のグリフのみを置き換える
+        let mut output_warnings = Vec::new();
+        let mut bitmap_overrides = Vec::new();
+        for (bitmap, chars) in bitmap_fonts.into_iter().zip(bitmap_chars) {
+            let Some(bitmap) = bitmap else {
+                continue;
+            };
+            let unwritten = chars.difference(&all_chars);
+            if !unwritten.is_empty() {
+                output_warnings.push(format!(
+                    "{} chars from bitmap font {} are not in {}.dynamicfont and are not written to any output",
+                    unwritten.count(),
+                    bitmap.face,
+                    self.file_name
+                ));
+            }
+            bitmap_overrides.push(BitmapOverride {
+                chars: chars.intersection(&all_chars),
+                font: bitmap,
+            });
+        }
+
+        // 隙間を埋めて範囲の数を減らす (レポートの文字数には含めない)
+        let regions = self
+            .region_merge
+            .map(|region_merge| region_merge.apply(&mut include_chars, &cmap_chars));
+
         // CharRangeListをCharacterRegionに変換
         let mut character_regions: CharacterRegions = include_chars
             .into_iter()
-            .zip(font_name_bundle_list.iter())
+            .zip(
+                font_name_bundle_list
+                    .iter()
+                    .zip(self.font_list.iter())
+                    .zip(region_sizes),
+            )
             // FontNameをコピーしながらflatten
             .flat_map(|(vec, count)| vec.into_iter().map(move |item| (item, count)))
             // CharacterRegionに変換
-            .map(|(range, font)| {
-                CharacterRegion::from_range(range, Some(font.full.to_string()), None, None)
+            .map(|(range, ((font_name, font), size))| {
+                CharacterRegion::from_range(
+                    range,
+                    Some(font_name.full.to_string()),
+                    size,
+                    font.style,
+                )
             })
-            .collect::<Vec<CharacterRegion>>()
+            .collect::<anyhow::Result<Vec<CharacterRegion>>>()?
             .into();
 
-        character_regions.ommit_base_font(&base_font);
+        character_regions.ommit_base_font(base_font);
+
+        let corpus = corpus_chars.map(|corpus_chars| {
+            let uncovered = corpus_chars.used.difference(&all_chars);
+            CorpusReport {
+                chars: corpus_chars.used.count(),
+                uncovered: uncovered.ranges().iter().map(|r| r.to_string()).collect(),
+                rare: corpus_chars.rare_chars(),
+            }
+        });
 
-        Ok(DynamicFont {
+        let report = FontReport {
+            file_name: self.file_name.clone(),
+            licenses,
+            coverage: PresetCoverage::compute_all(&all_chars),
+            corpus,
+            budget,
+            matched_sizes,
+            vertical: Some(vertical),
+            excluded_glyphs,
+            attribution,
+            regions,
+            output_warnings,
+        };
+
+        let font = DynamicFont {
             file_name: self.file_name,
             font_name: base_font.full.to_string(),
             size: self.size,
             spacing: self.spacing,
             use_kerning: self.use_kerning,
             style: self.style,
-            default_character: self.default_character,
+            default_character,
             vertical_offset: self.vertical_offset,
             character_regions,
+            bitmap_overrides,
+        };
+
+        Ok((font, report))
+    }
+}
+
+// 候補のうち、いずれかのフォントに含まれる最初の文字を選択
+fn select_default_character(
+    candidates: &[char],
+    font_names: &[FontNameBundle],
+    include_chars: &[CharRangeList],
+) -> anyhow::Result<char> {
+    if let Some(c) = candidates
+        .iter()
+        .find(|c| include_chars.iter().any(|chars| chars.contains(**c as u32)))
+    {
+        return Ok(*c);
+    }
+
+    // 各フォントが候補を含んでいるかを表示
+    let format_candidate = |c: &char| format!("'{}'(U+{:04X})", c, *c as u32);
+    let coverage = font_names
+        .iter()
+        .zip(include_chars)
+        .map(|(font_name, chars)| {
+            let candidates = candidates
+                .iter()
+                .map(|c| {
+                    let covered = if chars.contains(*c as u32) {
+                        "yes"
+                    } else {
+                        "no"
+                    };
+                    format!("{}: {}", format_candidate(c), covered)
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("  {}: {}", font_name.full, candidates)
         })
+        .collect::<Vec<_>>()
+        .join("\n");
+    anyhow::bail!(
+        "Default character not found in any font.\n\
+        Candidates: {}\n{}\n\
+        You must include at least one of the default characters in at least one font.",
+        candidates
+            .iter()
+            .map(format_candidate)
+            .collect::<Vec<_>>()
+            .join(", "),
+        coverage
+    );
+}
+
+// 各フォントに文字を割り当てる
+// preferで指定された文字を優先し、残りの文字は前のフォントから順に割り当てる
+fn assign_chars(
+    font_chars: Vec<CharRangeList>,
+    preferred_chars: Vec<CharRangeList>,
+) -> Vec<CharRangeList> {
+    let mut claimed_chars = CharRangeList::new();
+    let mut include_chars = vec![CharRangeList::new(); font_chars.len()];
+
+    for (preferred, include) in preferred_chars.iter().zip(include_chars.iter_mut()) {
+        let preferred = preferred.difference(&claimed_chars);
+        claimed_chars = claimed_chars.union(&preferred);
+        *include = preferred;
+    }
+
+    for (chars, include) in font_chars.iter().zip(include_chars.iter_mut()) {
+        let chars = chars.difference(&claimed_chars);
+        claimed_chars = claimed_chars.union(&chars);
+        *include = include.union(&chars);
     }
+
+    include_chars
 }
 
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "PascalCase")]
+// 各フォントに文字を割り当て、.dynamicfontの文字とビットマップフォントがグリフを置き換える文字を返す
+fn assign_chars_with_bitmaps(
+    font_chars: Vec<CharRangeList>,
+    preferred_chars: Vec<CharRangeList>,
+    is_bitmap: &[bool],
+) -> (Vec<CharRangeList>, Vec<CharRangeList>) {
+    // ビットマップフォントを除いて割り当てた文字を.dynamicfontに書き出す
+    let without_bitmaps = |lists: &[CharRangeList]| {
+        lists
+            .iter()
+            .zip(is_bitmap)
+            .map(|(chars, bitmap)| {
+                if *bitmap {
+                    CharRangeList::new()
+                } else {
+                    chars.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let include_chars = assign_chars(
+        without_bitmaps(&font_chars),
+        without_bitmaps(&preferred_chars),
+    );
+
+    // ビットマップフォントを含めて割り当てた場合にビットマップフォントが使用する文字
+    let bitmap_chars = assign_chars(font_chars, preferred_chars)
+        .into_iter()
+        .zip(is_bitmap)
+        .map(|(chars, bitmap)| if *bitmap { chars } else { CharRangeList::new() })
+        .collect();
+
+    (include_chars, bitmap_chars)
+}
+
+#[derive(Debug)]
 pub struct DynamicFont {
-    #[serde(skip)]
     pub file_name: String,
     pub font_name: String,
     pub size: f32,
@@ -244,11 +1015,21 @@ pub struct DynamicFont {
     pub default_character: char,
     pub vertical_offset: VerticalOffset,
     pub character_regions: CharacterRegions,
+    // ラスタライズする出力でグリフを置き換えるビットマップフォント (.dynamicfontには書き出さない)
+    pub bitmap_overrides: Vec<BitmapOverride>,
 }
 
-#[derive(Debug, Serialize)]
+// ビットマップフォントとグリフを置き換える文字
+#[derive(Debug)]
+pub struct BitmapOverride {
+    pub font: BitmapFont,
+    pub chars: CharRangeList,
+}
+
+#[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct CharacterRegions {
+    #[serde(default)]
     pub character_region: Vec<CharacterRegion>,
 }
 
@@ -282,9 +1063,40 @@ impl DynamicFont {
             },
         }
     }
+
+    // 文字のグリフを置き換えるビットマップフォント
+    pub fn bitmap_override(&self, c: char) -> Option<&BitmapFont> {
+        self.bitmap_overrides
+            .iter()
+            .find(|bitmap_override| bitmap_override.chars.contains(c as u32))
+            .map(|bitmap_override| &bitmap_override.font)
+    }
+
+    fn write_xml<W: Write>(&self, writer: &mut Writer<W>) -> io::Result<()> {
+        write_text(writer, "FontName", &self.font_name)?;
+        write_text(writer, "Size", &self.size.to_string())?;
+        write_text(writer, "Spacing", &self.spacing.to_string())?;
+        write_text(writer, "UseKerning", &self.use_kerning.to_string())?;
+        write_text(writer, "Style", &format!("{:?}", self.style))?;
+        write_char_reference(writer, "DefaultCharacter", self.default_character)?;
+        write_text(
+            writer,
+            "VerticalOffset",
+            &format!("{:?}", self.vertical_offset),
+        )?;
+        writer
+            .create_element("CharacterRegions")
+            .write_inner_content(|writer| {
+                for region in &self.character_regions.character_region {
+                    region.write_xml(writer)?;
+                }
+                Ok(())
+            })?;
+        Ok(())
+    }
 }
 
-#[derive(Debug, Serialize, PartialEq, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
 #[allow(unused)]
 pub enum FontStyle {
     Regular,
@@ -293,25 +1105,43 @@ pub enum FontStyle {
     BoldItalic,
 }
 
-#[derive(Debug, Serialize, PartialEq, Clone, Copy)]
+// フォールバックのフォント毎のサイズ
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[allow(unused)]
+pub enum FallbackSize {
+    Absolute(f32),
+    Scale(f32),
+}
+
+impl FallbackSize {
+    pub fn resolve(&self, base_size: f32) -> f32 {
+        match self {
+            FallbackSize::Absolute(size) => *size,
+            // 小数点以下2桁に丸める
+            FallbackSize::Scale(scale) => (base_size * scale * 100.0).round() / 100.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
 #[allow(unused)]
 pub enum VerticalOffset {
     DefaultFontAscent,
     MaxAscent,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct CharacterRegion {
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
     pub font_name: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
     pub size: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
     pub style: Option<FontStyle>,
-    #[serde(serialize_with = "serialize_char_as_xml_reference")]
+    #[serde(deserialize_with = "deserialize_char_from_xml")]
     pub start: char,
-    #[serde(serialize_with = "serialize_char_as_xml_reference")]
+    #[serde(deserialize_with = "deserialize_char_from_xml")]
     pub end: char,
 }
 
@@ -326,33 +1156,178 @@ impl CharacterRegion {
             end,
         }
     }
+
+    fn write_xml<W: Write>(&self, writer: &mut Writer<W>) -> io::Result<()> {
+        writer
+            .create_element("CharacterRegion")
+            .write_inner_content(|writer| {
+                if let Some(font_name) = &self.font_name {
+                    write_text(writer, "FontName", font_name)?;
+                }
+                if let Some(size) = self.size {
+                    write_text(writer, "Size", &size.to_string())?;
+                }
+                if let Some(style) = self.style {
+                    write_text(writer, "Style", &format!("{:?}", style))?;
+                }
+                write_char_reference(writer, "Start", self.start)?;
+                write_char_reference(writer, "End", self.end)?;
+                Ok(())
+            })?;
+        Ok(())
+    }
+}
+
+// 文字は文字参照として書き出す (空白文字等が読み込み時に削除されないように)
+pub fn write_char_reference<W: Write>(
+    writer: &mut Writer<W>,
+    name: &str,
+    c: char,
+) -> io::Result<()> {
+    let reference = format!("&#x{:X};", c as u32);
+    writer
+        .create_element(name)
+        .write_text_content(BytesText::from_escaped(reference))?;
+    Ok(())
+}
+
+// 文字列等はエスケープして書き出す
+pub fn write_text<W: Write>(writer: &mut Writer<W>, name: &str, text: &str) -> io::Result<()> {
+    writer
+        .create_element(name)
+        .write_text_content(BytesText::new(text))?;
+    Ok(())
 }
 
-fn serialize_char_as_xml_reference<S>(c: &char, serializer: S) -> Result<S::Ok, S::Error>
+// 文字参照はquick-xmlで文字に変換される
+// 二重にエスケープされた文字参照 (&amp;#x20;) も受け付ける
+fn deserialize_char_from_xml<'de, D>(deserializer: D) -> Result<char, D::Error>
 where
-    S: Serializer,
+    D: Deserializer<'de>,
 {
-    let xml_reference = format!("&#x{:X};", *c as u32);
-    // let xml_reference = c.escape_unicode().to_string();
-    // let xml_reference = c.to_string();
+    let text = String::deserialize(deserializer)?;
+    parse_xml_char(&text)
+        .ok_or_else(|| serde::de::Error::custom(format!("Expected a single character: {:?}", text)))
+}
+
+fn parse_xml_char(text: &str) -> Option<char> {
+    let mut chars = text.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(c);
+    }
+    let reference = text.strip_prefix("&#")?.strip_suffix(';')?;
+    let code_point = match reference.strip_prefix(['x', 'X']) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => reference.parse().ok()?,
+    };
+    char::from_u32(code_point)
+}
 
-    serializer.serialize_str(&xml_reference)
+// 1文字を表す要素
+const CHAR_ELEMENTS: [&str; 3] = ["Start", "End", "DefaultCharacter"];
+
+// quick-xmlは要素のテキストの前後の空白を除去するため、空白1文字の要素を文字参照に置き換える
+// 空の要素は空白と区別できないため例外
+fn escape_whitespace_chars(content: &str) -> anyhow::Result<String> {
+    let mut output = String::with_capacity(content.len());
+    let mut rest = content;
+    let mut line = 1;
+    while let Some(start) = rest.find('<') {
+        copy_prefix(&mut rest, start, &mut output, &mut line);
+
+        // コメントとCDATAの中は要素として扱わない
+        if let Some((open, close)) = [("<!--", "-->"), ("<![CDATA[", "]]>")]
+            .into_iter()
+            .find(|(open, _)| rest.starts_with(open))
+        {
+            let end = rest[open.len()..]
+                .find(close)
+                .with_context(|| format!("{} at line {} is not closed", open, line))?;
+            copy_prefix(
+                &mut rest,
+                open.len() + end + close.len(),
+                &mut output,
+                &mut line,
+            );
+            continue;
+        }
+
+        let name = CHAR_ELEMENTS.iter().find(|name| {
+            rest[1..].strip_prefix(**name).is_some_and(|after| {
+                after.starts_with(['>', '/']) || after.starts_with(char::is_whitespace)
+            })
+        });
+        let Some(name) = name else {
+            copy_prefix(&mut rest, 1, &mut output, &mut line);
+            continue;
+        };
+
+        let element_line = line;
+        let open_end = rest
+            .find('>')
+            .with_context(|| format!("<{}> at line {} is not closed", name, element_line))?;
+        if rest[..open_end].ends_with('/') {
+            anyhow::bail!("Empty <{}> element at line {}", name, element_line);
+        }
+        copy_prefix(&mut rest, open_end + 1, &mut output, &mut line);
+
+        let close_tag = format!("</{}>", name);
+        let text_len = rest
+            .find(&close_tag)
+            .with_context(|| format!("<{}> at line {} is not closed", name, element_line))?;
+        let text = &rest[..text_len];
+        if text.is_empty() {
+            anyhow::bail!("Empty <{}> element at line {}", name, element_line);
+        } else if text.trim().is_empty() {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => {
+                    output.push_str(&format!("&#x{:X};", c as u32));
+                    line += text.matches('\n').count();
+                    rest = &rest[text_len..];
+                }
+                _ => anyhow::bail!(
+                    "<{}> at line {} must contain a single character: {:?}",
+                    name,
+                    element_line,
+                    text
+                ),
+            }
+        } else {
+            copy_prefix(&mut rest, text_len, &mut output, &mut line);
+        }
+        copy_prefix(&mut rest, close_tag.len(), &mut output, &mut line);
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+// 先頭のlenバイトをそのまま書き出し、行番号を進める
+fn copy_prefix(rest: &mut &str, len: usize, output: &mut String, line: &mut usize) {
+    let (prefix, suffix) = rest.split_at(len);
+    output.push_str(prefix);
+    *line += prefix.matches('\n').count();
+    *rest = suffix;
 }
 
 impl CharacterRegion {
+    // サロゲート等のcharで表せない範囲はエラー
     pub fn from_range(
         range: CharRange,
         font_name: Option<String>,
         size: Option<f32>,
         style: Option<FontStyle>,
-    ) -> Self {
-        Self {
+    ) -> anyhow::Result<Self> {
+        let to_char = |c: u32| {
+            char::from_u32(c).with_context(|| format!("Invalid character in region: U+{:04X}", c))
+        };
+        Ok(Self {
             font_name,
             size,
             style,
-            start: char::from_u32(range.start).unwrap_or_default(),
-            end: char::from_u32(range.end).unwrap_or_default(),
-        }
+            start: to_char(range.start)?,
+            end: to_char(range.end)?,
+        })
     }
 }
 
@@ -377,12 +1352,355 @@ mod tests {
             default_character: '*',
             vertical_offset: VerticalOffset::DefaultFontAscent,
             character_regions: test_vec.into(),
+            bitmap_overrides: Vec::new(),
         };
 
-        let mut buffer = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
-        let mut ser = quick_xml::se::Serializer::new(&mut buffer);
-        ser.indent(' ', 2);
-        test_dynamic_font.pack().serialize(ser).unwrap();
-        println!("{}", buffer);
+        let expected = r#"<?xml version="1.0" encoding="utf-8"?>
+<XnaContent xmlns:Graphics="ReLogic.Content.Pipeline">
+  <Asset Type="Graphics:DynamicFontDescription">
+    <FontName>Arial</FontName>
+    <Size>16</Size>
+    <Spacing>0</Spacing>
+    <UseKerning>true</UseKerning>
+    <Style>Regular</Style>
+    <DefaultCharacter>&#x2A;</DefaultCharacter>
+    <VerticalOffset>DefaultFontAscent</VerticalOffset>
+    <CharacterRegions>
+      <CharacterRegion>
+        <Start>&#x61;</Start>
+        <End>&#x7A;</End>
+      </CharacterRegion>
+      <CharacterRegion>
+        <Start>&#x41;</Start>
+        <End>&#x5A;</End>
+      </CharacterRegion>
+    </CharacterRegions>
+  </Asset>
+</XnaContent>"#;
+        assert_eq!(test_dynamic_font.pack().to_xml().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_xml_whitespace_default_character() {
+        // 空白のDefaultCharacterも文字参照で書き出し、前後の空白の除去で消えないようにする
+        let font = DynamicFont {
+            file_name: "Test".to_string(),
+            font_name: "Noto Sans JP".to_string(),
+            size: 16.0,
+            spacing: 0.0,
+            use_kerning: true,
+            style: FontStyle::Regular,
+            default_character: '\u{3000}',
+            vertical_offset: VerticalOffset::DefaultFontAscent,
+            character_regions: vec![CharacterRegion::new('\u{3000}', '\u{3002}')].into(),
+            bitmap_overrides: Vec::new(),
+        };
+
+        let expected = r#"<?xml version="1.0" encoding="utf-8"?>
+<XnaContent xmlns:Graphics="ReLogic.Content.Pipeline">
+  <Asset Type="Graphics:DynamicFontDescription">
+    <FontName>Noto Sans JP</FontName>
+    <Size>16</Size>
+    <Spacing>0</Spacing>
+    <UseKerning>true</UseKerning>
+    <Style>Regular</Style>
+    <DefaultCharacter>&#x3000;</DefaultCharacter>
+    <VerticalOffset>DefaultFontAscent</VerticalOffset>
+    <CharacterRegions>
+      <CharacterRegion>
+        <Start>&#x3000;</Start>
+        <End>&#x3002;</End>
+      </CharacterRegion>
+    </CharacterRegions>
+  </Asset>
+</XnaContent>"#;
+        let xml = font.pack().to_xml().unwrap();
+        assert_eq!(xml, expected);
+
+        let read = XnaContent::from_xml(&xml).unwrap().asset.font;
+        assert_eq!(read.default_character, '\u{3000}');
+    }
+
+    #[test]
+    fn test_xml_escape() {
+        // フォント名の&や<はエスケープし、文字参照は二重にエスケープしない
+        let mut region = CharacterRegion::new('&', '<');
+        region.font_name = Some("ヒラギノ角ゴ <W3> & Co.".to_string());
+        region.size = Some(13.2);
+        region.style = Some(FontStyle::Bold);
+        let font = DynamicFont {
+            file_name: "Test".to_string(),
+            font_name: "Tom & Jerry".to_string(),
+            size: 12.0,
+            spacing: 0.0,
+            use_kerning: false,
+            style: FontStyle::Regular,
+            default_character: '&',
+            vertical_offset: VerticalOffset::MaxAscent,
+            character_regions: vec![CharacterRegion::new(' ', '~'), region].into(),
+            bitmap_overrides: Vec::new(),
+        };
+
+        let expected = r#"<?xml version="1.0" encoding="utf-8"?>
+<XnaContent xmlns:Graphics="ReLogic.Content.Pipeline">
+  <Asset Type="Graphics:DynamicFontDescription">
+    <FontName>Tom &amp; Jerry</FontName>
+    <Size>12</Size>
+    <Spacing>0</Spacing>
+    <UseKerning>false</UseKerning>
+    <Style>Regular</Style>
+    <DefaultCharacter>&#x26;</DefaultCharacter>
+    <VerticalOffset>MaxAscent</VerticalOffset>
+    <CharacterRegions>
+      <CharacterRegion>
+        <Start>&#x20;</Start>
+        <End>&#x7E;</End>
+      </CharacterRegion>
+      <CharacterRegion>
+        <FontName>ヒラギノ角ゴ &lt;W3&gt; &amp; Co.</FontName>
+        <Size>13.2</Size>
+        <Style>Bold</Style>
+        <Start>&#x26;</Start>
+        <End>&#x3C;</End>
+      </CharacterRegion>
+    </CharacterRegions>
+  </Asset>
+</XnaContent>"#;
+        let xml = font.pack().to_xml().unwrap();
+        assert_eq!(xml, expected);
+
+        let read = XnaContent::from_xml(&xml).unwrap().asset.font;
+        assert_eq!(read.font_name, "Tom & Jerry");
+        assert_eq!(read.default_character, '&');
+        let regions = &read.character_regions.character_region;
+        assert_eq!(
+            regions[1].font_name.as_deref(),
+            Some("ヒラギノ角ゴ <W3> & Co.")
+        );
+        assert_eq!((regions[1].start, regions[1].end), ('&', '<'));
+    }
+
+    #[test]
+    fn test_assign_chars_with_bitmaps() {
+        let list = |s: &str| s.parse::<CharRangeList>().unwrap();
+        let font_chars = vec![list("a-z"), list("0-9, a-c"), list("0-9, !")];
+        let (include_chars, bitmap_chars) = assign_chars_with_bitmaps(
+            font_chars,
+            vec![CharRangeList::new(); 3],
+            &[false, true, false],
+        );
+
+        // ビットマップフォントが使用する文字も後のベクターフォントから.dynamicfontに書き出す
+        assert_eq!(include_chars, vec![list("a-z"), list(""), list("0-9, !")]);
+        assert_eq!(bitmap_chars, vec![list(""), list("0-9"), list("")]);
+    }
+
+    #[test]
+    fn test_read_dynamic_font() {
+        let mut region = CharacterRegion::new('\u{3041}', '\u{3096}');
+        region.font_name = Some("Noto Sans JP".to_string());
+        region.size = Some(13.2);
+        region.style = Some(FontStyle::Bold);
+        let font = DynamicFont {
+            file_name: "Test".to_string(),
+            font_name: "Arial".to_string(),
+            size: 16.0,
+            spacing: 1.0,
+            use_kerning: false,
+            style: FontStyle::Italic,
+            default_character: '□',
+            vertical_offset: VerticalOffset::MaxAscent,
+            character_regions: vec![CharacterRegion::new(' ', '~'), region].into(),
+            bitmap_overrides: Vec::new(),
+        };
+
+        let read = XnaContent::from_xml(&font.pack().to_xml().unwrap()).unwrap();
+        assert_eq!(read.asset.r#type, "Graphics:DynamicFontDescription");
+        let read = read.asset.font;
+        assert_eq!(read.font_name, "Arial");
+        assert_eq!((read.size, read.spacing), (16.0, 1.0));
+        assert!(!read.use_kerning);
+        assert_eq!(read.style, FontStyle::Italic);
+        assert_eq!(read.default_character, '□');
+        assert_eq!(read.vertical_offset, VerticalOffset::MaxAscent);
+        let regions = &read.character_regions.character_region;
+        assert_eq!((regions[0].start, regions[0].end), (' ', '~'));
+        assert_eq!(regions[0].font_name, None);
+        assert_eq!(regions[1].font_name.as_deref(), Some("Noto Sans JP"));
+        assert_eq!(regions[1].size, Some(13.2));
+        assert_eq!(regions[1].style, Some(FontStyle::Bold));
+        assert_eq!((regions[1].start, regions[1].end), ('ぁ', 'ゖ'));
+    }
+
+    #[test]
+    fn test_read_hand_written_dynamic_font() {
+        // 10進数の文字参照、文字そのもの、省略された要素
+        let content = r#"<?xml version="1.0" encoding="utf-8"?>
+<XnaContent xmlns:Graphics="ReLogic.Content.Pipeline">
+  <Asset Type="Graphics:DynamicFontDescription">
+    <FontName>Andy Bold</FontName>
+    <Size>16</Size>
+    <DefaultCharacter>*</DefaultCharacter>
+    <CharacterRegions>
+      <CharacterRegion>
+        <Start>&#32;</Start>
+        <End>~</End>
+      </CharacterRegion>
+      <CharacterRegion>
+        <Start>&amp;#x3041;</Start>
+        <End>ゖ</End>
+      </CharacterRegion>
+    </CharacterRegions>
+  </Asset>
+</XnaContent>"#;
+        let font = XnaContent::from_xml(content).unwrap().asset.font;
+        assert_eq!(font.style, FontStyle::Regular);
+        assert_eq!(font.vertical_offset, VerticalOffset::DefaultFontAscent);
+        let regions = &font.character_regions.character_region;
+        assert_eq!((regions[0].start, regions[0].end), (' ', '~'));
+        assert_eq!((regions[1].start, regions[1].end), ('ぁ', 'ゖ'));
+
+        assert_eq!(parse_xml_char("&#x4E00;"), Some('一'));
+        assert_eq!(parse_xml_char("ab"), None);
+        assert_eq!(parse_xml_char(""), None);
+    }
+
+    #[test]
+    fn test_read_whitespace_and_empty_chars() {
+        let content = |start: &str, default_character: &str| {
+            format!(
+                "<XnaContent xmlns:Graphics=\"ReLogic.Content.Pipeline\">\n\
+                <Asset Type=\"Graphics:DynamicFontDescription\">\n\
+                <FontName>Arial</FontName>\n\
+                <Size>16</Size>\n\
+                {}\n\
+                <CharacterRegions>\n\
+                <CharacterRegion>\n\
+                {}\n\
+                <End>~</End>\n\
+                </CharacterRegion>\n\
+                </CharacterRegions>\n\
+                </Asset>\n\
+                </XnaContent>",
+                default_character, start
+            )
+        };
+
+        // 空白そのものは文字参照と同じく空白として読み込む
+        let font = XnaContent::from_xml(&content(
+            "<Start> </Start>",
+            "<DefaultCharacter>\u{3000}</DefaultCharacter>",
+        ))
+        .unwrap()
+        .asset
+        .font;
+        assert_eq!(font.character_regions.character_region[0].start, ' ');
+        assert_eq!(font.default_character, '\u{3000}');
+
+        // 空の要素は要素名と行番号を含むエラー
+        let error = XnaContent::from_xml(&content("<Start/>", ""))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "Empty <Start> element at line 8");
+        let error = XnaContent::from_xml(&content(
+            "<Start>a</Start>",
+            "<DefaultCharacter></DefaultCharacter>",
+        ))
+        .unwrap_err()
+        .to_string();
+        assert_eq!(error, "Empty <DefaultCharacter> element at line 5");
+
+        // コメントとCDATAの中の要素は読み込みに影響しない
+        let font = XnaContent::from_xml(&content(
+            "<Start>a</Start>",
+            "<!-- <Start/>\n<End></End> -->\n<DefaultCharacter><![CDATA[<]]></DefaultCharacter>",
+        ))
+        .unwrap()
+        .asset
+        .font;
+        assert_eq!(font.default_character, '<');
+        let error = XnaContent::from_xml(&content("<Start/>", "<!-- <Start/>\n<End></End> -->"))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "Empty <Start> element at line 9");
+    }
+
+    #[test]
+    fn test_region_from_invalid_range() {
+        let region = CharacterRegion::from_range(CharRange::new(0xD800, 0xDBFF), None, None, None);
+        assert!(region.is_err());
+    }
+
+    #[test]
+    fn test_select_default_character() {
+        let font_names = vec![FontNameBundle {
+            family: Cow::Borrowed("Test"),
+            postscript: Cow::Borrowed("Test"),
+            full: Cow::Borrowed("Test Regular"),
+        }];
+        let include_chars = vec![CharRangeList::from(vec![CharRange::new(0x20, 0x7E)])];
+
+        let c = select_default_character(&['□', '?', '*'], &font_names, &include_chars).unwrap();
+        assert_eq!(c, '?');
+
+        let error = select_default_character(&['□', '■'], &font_names, &include_chars)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Test Regular: '□'(U+25A1): no, '■'(U+25A0): no"));
+    }
+
+    #[test]
+    fn test_fallback_size() {
+        assert_eq!(FallbackSize::Absolute(18.0).resolve(16.0), 18.0);
+        assert_eq!(FallbackSize::Scale(1.1).resolve(12.0), 13.2);
+
+        let region = CharacterRegion::from_range(
+            CharRange::new(0x3000, 0x30FF),
+            Some("Noto Sans JP".to_string()),
+            Some(FallbackSize::Scale(1.1).resolve(12.0)),
+            Some(FontStyle::Bold),
+        )
+        .unwrap();
+        let mut writer = Writer::new(Vec::new());
+        region.write_xml(&mut writer).unwrap();
+        let buffer = String::from_utf8(writer.into_inner()).unwrap();
+        assert!(buffer.contains("<Size>13.2</Size>"));
+        assert!(buffer.contains("<Style>Bold</Style>"));
+    }
+
+    #[test]
+    fn test_assign_chars_with_prefer() {
+        let font_chars = vec![
+            CharRangeList::from(vec![
+                CharRange::new(0x20, 0x7E),
+                CharRange::new(0x3000, 0x30FF),
+            ]),
+            CharRangeList::from(vec![
+                CharRange::new(0x3000, 0x30FF),
+                CharRange::new(0xFF01, 0xFF5E),
+            ]),
+        ];
+        // 2番目のフォントから「」を使用
+        let preferred_chars = vec![
+            CharRangeList::new(),
+            CharRangeList::from(vec![CharRange::new(0x300C, 0x300D)]),
+        ];
+
+        let include_chars = assign_chars(font_chars, preferred_chars);
+        assert_eq!(
+            include_chars[0].ranges(),
+            &[
+                CharRange::new(0x20, 0x7E),
+                CharRange::new(0x3000, 0x300B),
+                CharRange::new(0x300E, 0x30FF),
+            ]
+        );
+        assert_eq!(
+            include_chars[1].ranges(),
+            &[
+                CharRange::new(0x300C, 0x300D),
+                CharRange::new(0xFF01, 0xFF5E)
+            ]
+        );
     }
 }
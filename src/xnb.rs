@@ -0,0 +1,305 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::atlas::GlyphAtlas;
+
+// XNA 4.0のアセンブリ
+const XNA_GRAPHICS: &str =
+    "Microsoft.Xna.Framework.Graphics, Version=4.0.0.0, Culture=neutral, PublicKeyToken=842cf8be1de50553";
+const XNA_FRAMEWORK: &str =
+    "Microsoft.Xna.Framework, Version=4.0.0.0, Culture=neutral, PublicKeyToken=842cf8be1de50553";
+const MSCORLIB: &str =
+    "mscorlib, Version=4.0.0.0, Culture=neutral, PublicKeyToken=b77a5c561934e089";
+
+// Reachプロファイルのテクスチャの最大サイズ
+const REACH_MAX_TEXTURE_SIZE: u32 = 2048;
+// HiDefプロファイルのテクスチャの最大サイズ
+pub const HIDEF_MAX_TEXTURE_SIZE: u32 = 4096;
+
+// SurfaceFormat.Color
+const SURFACE_FORMAT_COLOR: i32 = 0;
+
+// SpriteFontReaderが読み込む順番に並べたType Reader (番号+1がXNB内の型の番号)
+const SPRITE_FONT_READER: usize = 0;
+const TEXTURE_2D_READER: usize = 1;
+const RECTANGLE_LIST_READER: usize = 2;
+const CHAR_LIST_READER: usize = 4;
+const VECTOR3_LIST_READER: usize = 6;
+
+fn sprite_font_readers() -> Vec<String> {
+    vec![
+        format!(
+            "Microsoft.Xna.Framework.Content.SpriteFontReader, {}",
+            XNA_GRAPHICS
+        ),
+        format!(
+            "Microsoft.Xna.Framework.Content.Texture2DReader, {}",
+            XNA_GRAPHICS
+        ),
+        format!(
+            "Microsoft.Xna.Framework.Content.ListReader`1[[Microsoft.Xna.Framework.Rectangle, {}]]",
+            XNA_FRAMEWORK
+        ),
+        "Microsoft.Xna.Framework.Content.RectangleReader".to_string(),
+        format!(
+            "Microsoft.Xna.Framework.Content.ListReader`1[[System.Char, {}]]",
+            MSCORLIB
+        ),
+        "Microsoft.Xna.Framework.Content.CharReader".to_string(),
+        format!(
+            "Microsoft.Xna.Framework.Content.ListReader`1[[Microsoft.Xna.Framework.Vector3, {}]]",
+            XNA_FRAMEWORK
+        ),
+        "Microsoft.Xna.Framework.Content.Vector3Reader".to_string(),
+    ]
+}
+
+// .NETのBinaryWriterと同じ形式で書き込む
+#[derive(Debug, Default)]
+struct XnbWriter {
+    buffer: Vec<u8>,
+}
+
+impl XnbWriter {
+    fn write_u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    fn write_i32(&mut self, value: i32) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // 7bitずつ区切った可変長の整数
+    fn write_7bit_int(&mut self, mut value: u32) {
+        while value >= 0x80 {
+            self.write_u8((value as u8 & 0x7F) | 0x80);
+            value >>= 7;
+        }
+        self.write_u8(value as u8);
+    }
+
+    fn write_string(&mut self, value: &str) {
+        self.write_7bit_int(value.len() as u32);
+        self.buffer.extend_from_slice(value.as_bytes());
+    }
+
+    // BinaryWriter.Write(char)はUTF-8で書き込む
+    fn write_char(&mut self, c: char) {
+        let mut bytes = [0; 4];
+        self.buffer
+            .extend_from_slice(c.encode_utf8(&mut bytes).as_bytes());
+    }
+
+    // ContentReader.ReadObjectで読み込む値の型の番号
+    fn write_type_id(&mut self, reader: usize) {
+        self.write_7bit_int(reader as u32 + 1);
+    }
+
+    fn write_rectangle(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.write_i32(x);
+        self.write_i32(y);
+        self.write_i32(width);
+        self.write_i32(height);
+    }
+}
+
+// Microsoft.Xna.Framework.Graphics.SpriteFontのXNB
+#[derive(Debug, Clone)]
+pub struct SpriteFontXnb<'a> {
+    pub file_name: &'a str,
+    pub atlas: &'a GlyphAtlas,
+    pub spacing: f32,
+    pub use_kerning: bool,
+    pub default_character: Option<char>,
+}
+
+impl SpriteFontXnb<'_> {
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let atlas = self.atlas;
+        let [page] = atlas.pages.as_slice() else {
+            anyhow::bail!(
+                "{}: SpriteFont needs all glyphs in one texture, but {} pages are used",
+                self.file_name,
+                atlas.pages.len()
+            );
+        };
+        let mut content = XnbWriter::default();
+
+        // Type Readerと共有リソースの数
+        let readers = sprite_font_readers();
+        content.write_7bit_int(readers.len() as u32);
+        for reader in &readers {
+            content.write_string(reader);
+            content.write_i32(0);
+        }
+        content.write_7bit_int(0);
+
+        content.write_type_id(SPRITE_FONT_READER);
+
+        // Texture2D (白色、乗算済みアルファ)
+        content.write_type_id(TEXTURE_2D_READER);
+        content.write_i32(SURFACE_FORMAT_COLOR);
+        content.write_u32(atlas.width);
+        content.write_u32(atlas.height);
+        content.write_u32(1);
+        content.write_u32(atlas.width * atlas.height * 4);
+        for &alpha in page {
+            content.buffer.extend_from_slice(&[alpha; 4]);
+        }
+
+        // テクスチャ上のグリフの位置
+        content.write_type_id(RECTANGLE_LIST_READER);
+        content.write_u32(atlas.glyphs.len() as u32);
+        for glyph in &atlas.glyphs {
+            content.write_rectangle(
+                glyph.x as i32,
+                glyph.y as i32,
+                glyph.width as i32,
+                glyph.height as i32,
+            );
+        }
+
+        // 描画位置からのずれ (Kerningを使用しない場合は左端のずれを含む)
+        content.write_type_id(RECTANGLE_LIST_READER);
+        content.write_u32(atlas.glyphs.len() as u32);
+        for glyph in &atlas.glyphs {
+            let x = if self.use_kerning { 0 } else { glyph.left };
+            content.write_rectangle(x, glyph.top, glyph.width as i32, atlas.line_height);
+        }
+
+        content.write_type_id(CHAR_LIST_READER);
+        content.write_u32(atlas.glyphs.len() as u32);
+        for glyph in &atlas.glyphs {
+            content.write_char(glyph.c);
+        }
+
+        content.write_i32(atlas.line_height);
+        content.write_f32(self.spacing);
+
+        // 左のベアリング、幅、右のベアリング
+        content.write_type_id(VECTOR3_LIST_READER);
+        content.write_u32(atlas.glyphs.len() as u32);
+        for glyph in &atlas.glyphs {
+            let (left, width, right) = if self.use_kerning {
+                let width = glyph.width as f32;
+                (
+                    glyph.left as f32,
+                    width,
+                    glyph.advance - glyph.left as f32 - width,
+                )
+            } else {
+                (0.0, glyph.advance, 0.0)
+            };
+            content.write_f32(left);
+            content.write_f32(width);
+            content.write_f32(right);
+        }
+
+        let default_character = self.default_character.filter(|c| atlas.glyph(*c).is_some());
+        content.write_bool(default_character.is_some());
+        if let Some(c) = default_character {
+            content.write_char(c);
+        }
+
+        // ヘッダー (Windows、XNA 4.0、非圧縮)
+        let mut xnb = XnbWriter::default();
+        xnb.buffer.extend_from_slice(b"XNBw");
+        xnb.write_u8(5);
+        let hidef = atlas.width.max(atlas.height) > REACH_MAX_TEXTURE_SIZE;
+        xnb.write_u8(if hidef { 0x01 } else { 0x00 });
+        xnb.write_u32((10 + content.buffer.len()) as u32);
+        xnb.buffer.extend(content.buffer);
+        Ok(xnb.buffer)
+    }
+
+    pub fn write(&self, directory: &Path) -> anyhow::Result<PathBuf> {
+        fs::create_dir_all(directory)?;
+        let path = directory.join(format!("{}.xnb", self.file_name));
+        fs::write(&path, self.to_bytes()?)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atlas::AtlasGlyph;
+
+    #[test]
+    fn test_7bit_int() {
+        let mut writer = XnbWriter::default();
+        writer.write_7bit_int(0x7F);
+        writer.write_7bit_int(0x80);
+        writer.write_7bit_int(300);
+        assert_eq!(writer.buffer, vec![0x7F, 0x80, 0x01, 0xAC, 0x02]);
+    }
+
+    #[test]
+    fn test_sprite_font_xnb() {
+        let atlas = GlyphAtlas {
+            width: 2,
+            height: 1,
+            pages: vec![vec![0, 255]],
+            glyphs: vec![AtlasGlyph {
+                c: 'あ',
+                page: 0,
+                x: 1,
+                y: 0,
+                width: 1,
+                height: 1,
+                left: 1,
+                top: 3,
+                advance: 4.0,
+            }],
+            line_height: 5,
+            base: 4,
+            warnings: Vec::new(),
+        };
+        let xnb = SpriteFontXnb {
+            file_name: "Test",
+            atlas: &atlas,
+            spacing: 0.0,
+            use_kerning: true,
+            default_character: Some('*'),
+        };
+        let bytes = xnb.to_bytes().unwrap();
+
+        assert_eq!(&bytes[..6], b"XNBw\x05\x00");
+        assert_eq!(
+            u32::from_le_bytes(bytes[6..10].try_into().unwrap()) as usize,
+            bytes.len()
+        );
+        // 8個のType Reader
+        assert_eq!(bytes[10], 8);
+        // グリフの無いDefaultCharacterは書き込まない
+        assert_eq!(bytes.last(), Some(&0));
+        // テクスチャの2ピクセル目
+        let texture = bytes
+            .windows(8)
+            .position(|window| window == [0, 0, 0, 0, 255, 255, 255, 255])
+            .unwrap();
+        assert_eq!(
+            u32::from_le_bytes(bytes[texture - 4..texture].try_into().unwrap()),
+            8
+        );
+        // 文字はUTF-8、カーニングは (1, 1, 2)
+        let c = bytes.windows(3).position(|w| w == "あ".as_bytes()).unwrap();
+        let kerning = &bytes[bytes.len() - 13..bytes.len() - 1];
+        assert_eq!(kerning, [1f32, 1.0, 2.0].map(f32::to_le_bytes).concat());
+        assert!(c < bytes.len() - 13);
+    }
+}